@@ -6,13 +6,15 @@ use hyper::{
     Method, Request as HyperRequest,
     body::Bytes,
     client::conn::http1::handshake,
-    header::{ACCEPT, CONTENT_LENGTH, HOST, HeaderName, HeaderValue, USER_AGENT},
+    header::{
+        ACCEPT, CONTENT_LENGTH, HOST, HeaderName, HeaderValue, PROXY_AUTHORIZATION, USER_AGENT,
+    },
 };
 
 use url::Url;
 
 use crate::{
-    client::stream::HttpStream,
+    cookies::CookieJar,
     shared::{hyper::HyperIo, request::Request, response::Response},
 };
 
@@ -85,8 +87,17 @@ async fn fetch_inner(
     mut url: Url,
     mut request: Request,
 ) -> Result<Response, String> {
+    // Cookies set during redirects must be sent with any following requests
+    let jar = CookieJar::default();
+
     loop {
-        let stream = HttpStream::connect_url(url.clone())
+        let host = url.host_str().unwrap_or_default();
+        let port = url.port_or_known_default().unwrap_or_default();
+        let proxy = request
+            .proxy
+            .resolve(None, url.scheme(), host, port)
+            .map_err(|e| e.to_string())?;
+        let (stream, proxy_auth) = super::connect_http(&url, proxy.as_ref())
             .await
             .map_err(|e| e.to_string())?;
 
@@ -101,9 +112,15 @@ async fn fetch_inner(
             let host = HeaderValue::from_str(host).unwrap();
             parts.headers.insert(HOST, host);
         }
+        jar.apply_to_headers(&url, &mut parts.headers);
+        if let Some(auth) = proxy_auth {
+            let auth = HeaderValue::from_str(&auth).map_err(|e| e.to_string())?;
+            parts.headers.insert(PROXY_AUTHORIZATION, auth);
+        }
 
         let data = HyperRequest::from_parts(parts, Full::new(body.into_bytes()));
         let incoming = sender.send_request(data).await.map_err(|e| e.to_string())?;
+        jar.set_from_headers(&url, incoming.headers());
 
         if super::try_follow_redirect(&mut url, &mut request, &incoming)
             .map_err(ToString::to_string)?
@@ -1,3 +1,5 @@
+use std::io::Result as IoResult;
+
 use hyper::{Method, Response as HyperResponse, Uri, body::Incoming, header::LOCATION};
 
 use mlua::prelude::*;
@@ -6,15 +8,19 @@ use url::Url;
 use crate::{
     body::ReadableBody,
     client::{
-        stream::{MaybeTlsStream, WsStream},
+        proxy::{Proxy, ProxyConfig, ProxyKind},
+        stream::{HttpStream, MaybeTlsStream, WsStream},
         tcp::TcpConfig,
+        ws::WsConfig,
     },
     shared::{request::Request, tcp::Tcp, websocket::Websocket},
 };
 
+pub mod proxy;
 pub mod rustls;
 pub mod stream;
 pub mod tcp;
+pub mod ws;
 
 mod fetch;
 mod send;
@@ -25,20 +31,25 @@ pub use self::send::send;
 const MAX_REDIRECTS: usize = 10;
 
 /**
-    Connects to a websocket at the given URL.
+    Connects to a websocket at the given URL, using the given config.
 */
-pub async fn connect_ws(url: Url) -> LuaResult<Websocket<WsStream>> {
-    let stream = WsStream::connect_url(url).await?;
+pub async fn connect_ws(lua: &Lua, url: Url, config: WsConfig) -> LuaResult<Websocket<WsStream>> {
+    let proxy = resolve_url_proxy(lua, &url, &config.proxy)?;
+    let stream = WsStream::connect_url(url, proxy.as_ref()).await?;
     Ok(Websocket::from(stream))
 }
 
 /**
     Connects using plain TCP using the given host, port, and config.
 */
-pub async fn connect_tcp(host: String, port: u16, config: TcpConfig) -> LuaResult<Tcp> {
+pub async fn connect_tcp(lua: &Lua, host: String, port: u16, config: TcpConfig) -> LuaResult<Tcp> {
     let tls = config.tls.unwrap_or_default();
 
-    let stream = MaybeTlsStream::connect(&host, port, tls)
+    let proxy = config
+        .proxy
+        .resolve(Some(lua), "tcp", &host, port)
+        .into_lua_err()?;
+    let stream = MaybeTlsStream::connect(&host, port, tls, proxy.as_ref())
         .await
         .into_lua_err()?;
 
@@ -49,6 +60,34 @@ pub async fn connect_tcp(host: String, port: u16, config: TcpConfig) -> LuaResul
     Ok(Tcp::from(stream))
 }
 
+/**
+    Resolves the proxy to use for a connection to the given URL.
+*/
+fn resolve_url_proxy(lua: &Lua, url: &Url, config: &ProxyConfig) -> LuaResult<Option<Proxy>> {
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or_default();
+    config
+        .resolve(Some(lua), url.scheme(), host, port)
+        .into_lua_err()
+}
+
+/**
+    Connects to the given URL for sending an HTTP request, optionally through a proxy.
+
+    Plain HTTP requests through an HTTP proxy are forwarded by the proxy instead of
+    being tunneled, in which case the value for the `Proxy-Authorization` header
+    that must be sent along with the request, if any, is also returned.
+*/
+async fn connect_http(url: &Url, proxy: Option<&Proxy>) -> IoResult<(HttpStream, Option<String>)> {
+    match proxy {
+        Some(proxy) if proxy.kind == ProxyKind::Http && url.scheme() == "http" => {
+            let stream = proxy.connect_direct().await?;
+            Ok((HttpStream::from(stream), proxy.authorization()))
+        }
+        proxy => Ok((HttpStream::connect_url(url.clone(), proxy).await?, None)),
+    }
+}
+
 fn try_follow_redirect(
     url: &mut Url,
     request: &mut Request,
@@ -71,6 +110,7 @@ fn try_follow_redirect(
 
         if new_method == Method::GET {
             *request.inner.body_mut() = ReadableBody::empty();
+            request.multipart = None;
         }
 
         *request.inner.method_mut() = new_method;
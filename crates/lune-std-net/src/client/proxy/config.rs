@@ -0,0 +1,195 @@
+use std::io::Result;
+
+use lune_utils::process::ProcessEnv;
+use mlua::prelude::*;
+
+use super::{Proxy, no_proxy::should_bypass};
+
+/**
+    Configuration for which proxy, if any, to use for a connection.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ProxyConfig {
+    /**
+        Discover the proxy using the `http_proxy`, `HTTPS_PROXY`,
+        `ALL_PROXY`, and `NO_PROXY` environment variables.
+    */
+    #[default]
+    Environment,
+    /**
+        Never use a proxy, ignoring any environment variables.
+    */
+    Disabled,
+    /**
+        Always use the given proxy.
+    */
+    Explicit(Proxy),
+}
+
+impl ProxyConfig {
+    /**
+        Resolves the proxy to use for a connection using the given scheme, to the given host and port.
+
+        Environment variables are read from the environment that the runtime for the
+        given Lua state was created with, if available, and otherwise from the current process.
+
+        # Errors
+
+        Errors if a proxy environment variable is set, but is not a valid proxy URL.
+    */
+    pub fn resolve(
+        &self,
+        lua: Option<&Lua>,
+        scheme: &str,
+        host: &str,
+        port: u16,
+    ) -> Result<Option<Proxy>> {
+        match self {
+            Self::Disabled => Ok(None),
+            Self::Explicit(proxy) => Ok(Some(proxy.clone())),
+            Self::Environment => {
+                let var = |name: &str, upper: bool| {
+                    env_var(lua, name, upper).filter(|v| !v.trim().is_empty())
+                };
+
+                // NOTE: Only the lowercase variant of `http_proxy` is used, since the uppercase
+                // one may be set from the `Proxy` header of incoming requests, in CGI and
+                // similar environments, which would let anyone redirect our requests (httpoxy)
+                let proxy = match scheme {
+                    "http" | "ws" => var("http_proxy", false),
+                    "https" | "wss" => var("https_proxy", true),
+                    _ => None,
+                }
+                .or_else(|| var("all_proxy", true));
+
+                let Some(proxy) = proxy else {
+                    return Ok(None);
+                };
+                if var("no_proxy", true)
+                    .is_some_and(|no_proxy| should_bypass(&no_proxy, host, port))
+                {
+                    return Ok(None);
+                }
+
+                Proxy::parse(&proxy).map(Some)
+            }
+        }
+    }
+}
+
+impl FromLua for ProxyConfig {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil | LuaValue::Boolean(true) => Ok(Self::Environment),
+            LuaValue::Boolean(false) => Ok(Self::Disabled),
+            LuaValue::String(s) => {
+                let proxy = Proxy::parse(&s.to_str()?).into_lua_err()?;
+                Ok(Self::Explicit(proxy))
+            }
+            LuaValue::Table(t) => {
+                let url = t.get::<LuaString>("url")?;
+                let mut proxy = Proxy::parse(&url.to_str()?).into_lua_err()?;
+                if let Some(username) = t.get::<Option<String>>("username")? {
+                    let password = t.get::<Option<String>>("password")?.unwrap_or_default();
+                    proxy.credentials = Some((username, password));
+                }
+                Ok(Self::Explicit(proxy))
+            }
+            v => Err(LuaError::FromLuaConversionError {
+                from: v.type_name(),
+                to: "ProxyConfig".to_string(),
+                message: Some(format!(
+                    "Invalid proxy - expected string, table, or boolean, got {}",
+                    v.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn env_var(lua: Option<&Lua>, name: &str, upper: bool) -> Option<String> {
+    let env = lua.and_then(|lua| lua.app_data_ref::<ProcessEnv>());
+    // Lowercase variables take precedence, same as in curl and most other tools
+    let upper = upper.then(|| name.to_ascii_uppercase());
+    [Some(name), upper.as_deref()]
+        .into_iter()
+        .flatten()
+        .find_map(|key| {
+            let value = match &env {
+                Some(env) => env.get_value(key),
+                None => std::env::var_os(key),
+            };
+            value.and_then(|v| v.into_string().ok())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(vars: &[(&str, &str)], scheme: &str, host: &str) -> Option<Proxy> {
+        let lua = Lua::new();
+        let env = ProcessEnv::empty();
+        for (key, value) in vars {
+            env.set_value(*key, *value);
+        }
+        lua.set_app_data(env);
+        ProxyConfig::Environment
+            .resolve(Some(&lua), scheme, host, 80)
+            .unwrap()
+    }
+
+    fn proxy_host(proxy: Option<Proxy>) -> Option<String> {
+        proxy.map(|proxy| proxy.host)
+    }
+
+    #[test]
+    fn environment_by_scheme() {
+        let vars = [
+            ("http_proxy", "http://plain.proxy"),
+            ("HTTPS_PROXY", "http://secure.proxy"),
+            ("ALL_PROXY", "socks5://fallback.proxy"),
+        ];
+        let host = |scheme| proxy_host(resolve(&vars, scheme, "example.com"));
+        assert_eq!(host("http").as_deref(), Some("plain.proxy"));
+        assert_eq!(host("ws").as_deref(), Some("plain.proxy"));
+        assert_eq!(host("https").as_deref(), Some("secure.proxy"));
+        assert_eq!(host("wss").as_deref(), Some("secure.proxy"));
+        assert_eq!(host("tcp").as_deref(), Some("fallback.proxy"));
+    }
+
+    #[test]
+    fn environment_lowercase_first() {
+        let vars = [
+            ("https_proxy", "http://lower.proxy"),
+            ("HTTPS_PROXY", "http://upper.proxy"),
+        ];
+        let proxy = resolve(&vars, "https", "example.com");
+        assert_eq!(proxy_host(proxy).as_deref(), Some("lower.proxy"));
+    }
+
+    #[test]
+    fn environment_ignores_uppercase_http_proxy() {
+        let vars = [("HTTP_PROXY", "http://attacker.proxy")];
+        assert_eq!(resolve(&vars, "http", "example.com"), None);
+
+        let vars = [
+            ("HTTP_PROXY", "http://attacker.proxy"),
+            ("ALL_PROXY", "http://fallback.proxy"),
+        ];
+        let proxy = resolve(&vars, "http", "example.com");
+        assert_eq!(proxy_host(proxy).as_deref(), Some("fallback.proxy"));
+    }
+
+    #[test]
+    fn environment_no_proxy() {
+        let vars = [
+            ("ALL_PROXY", "http://fallback.proxy"),
+            ("NO_PROXY", "localhost,.internal,10.0.0.0/8"),
+        ];
+        assert_eq!(resolve(&vars, "https", "localhost"), None);
+        assert_eq!(resolve(&vars, "https", "api.internal"), None);
+        assert_eq!(resolve(&vars, "https", "10.1.2.3"), None);
+        assert!(resolve(&vars, "https", "example.com").is_some());
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{Error, ErrorKind, Result};
+
+use async_net::TcpStream;
+use futures_lite::prelude::*;
+
+use super::Proxy;
+
+const MAX_RESPONSE_HEAD_SIZE: usize = 16 * 1024;
+
+/**
+    Performs an HTTP `CONNECT` handshake on a stream connected to an
+    HTTP proxy, after which the stream is tunneled to the target host.
+*/
+pub async fn handshake(stream: &mut TcpStream, proxy: &Proxy, host: &str, port: u16) -> Result<()> {
+    let authority = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some(auth) = proxy.authorization() {
+        request.push_str("Proxy-Authorization: ");
+        request.push_str(&auth);
+        request.push_str("\r\n");
+    }
+    request.push_str("\r\n");
+
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    // Read the response head one byte at a time, since anything
+    // after it belongs to the tunneled connection and must not be lost
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "proxy closed the connection during CONNECT",
+            ));
+        }
+        head.push(byte[0]);
+        if head.len() > MAX_RESPONSE_HEAD_SIZE {
+            return Err(Error::other("proxy sent an oversized CONNECT response"));
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| Error::other(format!("invalid CONNECT response - '{status_line}'")))?;
+
+    match status {
+        200..=299 => Ok(()),
+        407 => Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "proxy authentication required",
+        )),
+        _ => Err(Error::other(format!(
+            "proxy refused CONNECT to {authority} - '{status_line}'"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread::{self, JoinHandle},
+    };
+
+    use super::*;
+
+    /**
+        Spawns an HTTP proxy that answers a single `CONNECT` request with the
+        given status line, and then echoes back anything sent through the tunnel.
+
+        Returns the proxy, and a handle resolving to the head of the received request.
+    */
+    fn spawn_proxy(status_line: &'static str) -> (Proxy, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                head.push(byte[0]);
+            }
+            stream
+                .write_all(format!("{status_line}\r\n\r\n").as_bytes())
+                .unwrap();
+            let mut echo = [0u8; 5];
+            if stream.read_exact(&mut echo).is_ok() {
+                stream.write_all(&echo).unwrap();
+            }
+            String::from_utf8(head).unwrap()
+        });
+        let proxy = Proxy::parse(&format!("http://127.0.0.1:{port}")).unwrap();
+        (proxy, handle)
+    }
+
+    #[test]
+    fn connect_tunnels_to_target() {
+        let (mut proxy, server) = spawn_proxy("HTTP/1.1 200 Connection established");
+        proxy.credentials = Some(("user".to_string(), "hunter2".to_string()));
+
+        async_io::block_on(async {
+            let mut stream = proxy.connect("example.com", 443).await.unwrap();
+            stream.write_all(b"hello").await.unwrap();
+            let mut echo = [0u8; 5];
+            stream.read_exact(&mut echo).await.unwrap();
+            assert_eq!(&echo, b"hello");
+        });
+
+        let head = server.join().unwrap();
+        assert!(head.starts_with("CONNECT example.com:443 HTTP/1.1\r\n"));
+        assert!(head.contains("Host: example.com:443\r\n"));
+        assert!(head.contains("Proxy-Authorization: Basic dXNlcjpodW50ZXIy\r\n"));
+    }
+
+    #[test]
+    fn connect_brackets_ipv6_targets() {
+        let (proxy, server) = spawn_proxy("HTTP/1.1 200 OK");
+        async_io::block_on(proxy.connect("[::1]", 8080)).unwrap();
+        assert!(
+            server
+                .join()
+                .unwrap()
+                .starts_with("CONNECT [::1]:8080 HTTP/1.1\r\n")
+        );
+    }
+
+    #[test]
+    fn connect_refused() {
+        let (proxy, server) = spawn_proxy("HTTP/1.1 407 Proxy Authentication Required");
+        let err = async_io::block_on(proxy.connect("example.com", 443)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        server.join().unwrap();
+
+        let (proxy, server) = spawn_proxy("HTTP/1.1 403 Forbidden");
+        let err = async_io::block_on(proxy.connect("example.com", 443)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("refused CONNECT to example.com:443")
+        );
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+use std::net::IpAddr;
+
+/**
+    Checks if the given host and port should bypass any proxy,
+    according to a `NO_PROXY` environment variable value.
+
+    Supports the same formats as most other tools, such as `curl`:
+
+    - `*` to bypass the proxy for all hosts
+    - Domain names, matching the domain itself and any subdomains, with an optional leading dot
+    - IP addresses, and IP ranges in CIDR notation
+    - Any of the above with a `:port` suffix, only matching that port
+*/
+pub fn should_bypass(no_proxy: &str, host: &str, port: u16) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.to_ascii_lowercase();
+    let host_ip = host.parse::<IpAddr>().ok();
+
+    no_proxy
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+
+            let (pattern, entry_port) = split_port(entry);
+            if entry_port.is_some_and(|p| p != port) {
+                return false;
+            }
+
+            if let Some(ip) = host_ip {
+                match_ip(pattern, ip)
+            } else {
+                let pattern = pattern.trim_start_matches('.').to_ascii_lowercase();
+                host == pattern
+                    || host
+                        .strip_suffix(&pattern)
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            }
+        })
+}
+
+fn split_port(entry: &str) -> (&str, Option<u16>) {
+    // Bracketed IPv6 address, with or without a port
+    if let Some(rest) = entry.strip_prefix('[') {
+        if let Some((addr, after)) = rest.split_once(']') {
+            let port = after.strip_prefix(':').and_then(|p| p.parse().ok());
+            return (addr, port);
+        }
+        return (entry, None);
+    }
+    // Plain IPv6 addresses and ranges contain multiple colons, and no port
+    if entry.matches(':').count() > 1 {
+        return (entry, None);
+    }
+    match entry.rsplit_once(':') {
+        Some((pattern, port)) => match port.parse() {
+            Ok(port) => (pattern, Some(port)),
+            Err(_) => (entry, None),
+        },
+        None => (entry, None),
+    }
+}
+
+fn match_ip(pattern: &str, ip: IpAddr) -> bool {
+    let (addr, prefix_len) = match pattern.split_once('/') {
+        Some((addr, len)) => match len.parse::<u32>() {
+            Ok(len) => (addr, Some(len)),
+            Err(_) => return false,
+        },
+        None => (pattern, None),
+    };
+    let Ok(addr) = addr.parse::<IpAddr>() else {
+        return false;
+    };
+
+    match (addr, ip) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let len = prefix_len.unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - len).unwrap_or(0);
+            (u32::from(a) & mask) == (u32::from(b) & mask)
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let len = prefix_len.unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - len).unwrap_or(0);
+            (u128::from(a) & mask) == (u128::from(b) & mask)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bypass_wildcard() {
+        assert!(should_bypass("*", "example.com", 80));
+        assert!(should_bypass("other.com, *", "127.0.0.1", 443));
+    }
+
+    #[test]
+    fn bypass_domains() {
+        assert!(should_bypass("example.com", "example.com", 80));
+        assert!(should_bypass("example.com", "api.example.com", 80));
+        assert!(should_bypass(".example.com", "api.example.com", 80));
+        assert!(should_bypass("EXAMPLE.com", "Api.Example.COM", 80));
+        assert!(should_bypass("other.com,example.com", "example.com", 80));
+        assert!(should_bypass("other.com example.com", "example.com", 80));
+        assert!(!should_bypass("example.com", "badexample.com", 80));
+        assert!(!should_bypass("api.example.com", "example.com", 80));
+        assert!(!should_bypass("", "example.com", 80));
+    }
+
+    #[test]
+    fn bypass_ips() {
+        assert!(should_bypass("127.0.0.1", "127.0.0.1", 80));
+        assert!(should_bypass("10.0.0.0/8", "10.1.2.3", 80));
+        assert!(!should_bypass("10.0.0.0/8", "11.1.2.3", 80));
+        assert!(should_bypass("::1", "[::1]", 80));
+        assert!(should_bypass("fd00::/8", "fd12::1", 80));
+        assert!(!should_bypass("127.0.0.1", "::1", 80));
+        assert!(!should_bypass("127.0.0.1", "localhost", 80));
+    }
+
+    #[test]
+    fn bypass_ports() {
+        assert!(should_bypass("example.com:8080", "example.com", 8080));
+        assert!(!should_bypass("example.com:8080", "example.com", 80));
+        assert!(should_bypass("127.0.0.1:8080", "127.0.0.1", 8080));
+        assert!(should_bypass("[::1]:8080", "::1", 8080));
+        assert!(!should_bypass("[::1]:8080", "::1", 80));
+    }
+}
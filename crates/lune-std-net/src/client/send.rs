@@ -1,21 +1,20 @@
-use http_body_util::Full;
+use http_body_util::{Either, Full};
 use hyper::{
     Method, Request as HyperRequest,
     client::conn::http1::handshake,
-    header::{ACCEPT, CONTENT_LENGTH, HOST, HeaderValue, USER_AGENT},
+    header::{
+        ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, HOST, HeaderValue, PROXY_AUTHORIZATION, USER_AGENT,
+    },
 };
 
 use mlua::prelude::*;
 use url::Url;
 
-use crate::{
-    client::stream::HttpStream,
-    shared::{
-        headers::create_user_agent_header,
-        hyper::{HyperExecutor, HyperIo},
-        request::Request,
-        response::Response,
-    },
+use crate::shared::{
+    headers::create_user_agent_header,
+    hyper::{HyperExecutor, HyperIo},
+    request::Request,
+    response::Response,
 };
 
 /**
@@ -39,7 +38,10 @@ pub async fn send(mut request: Request, lua: Lua) -> LuaResult<Response> {
         let ua = HeaderValue::from_str(&ua).unwrap();
         request.inner.headers_mut().insert(USER_AGENT, ua);
     }
-    if !request.headers().contains_key(CONTENT_LENGTH.as_str()) && request.method() != Method::GET {
+    if !request.headers().contains_key(CONTENT_LENGTH.as_str())
+        && request.method() != Method::GET
+        && request.multipart.is_none()
+    {
         let len = request.body().len().to_string();
         let len = HeaderValue::from_str(&len).unwrap();
         request.inner.headers_mut().insert(CONTENT_LENGTH, len);
@@ -49,9 +51,21 @@ pub async fn send(mut request: Request, lua: Lua) -> LuaResult<Response> {
         request.inner.headers_mut().insert(ACCEPT, accept);
     }
 
+    // Cookies set during redirects must be sent with any following requests,
+    // so we always use a jar, which is discarded if one was not given to us
+    let jar = request.cookies.clone().unwrap_or_default();
+
     // ... we can now safely continue and send the request
-    loop {
-        let stream = HttpStream::connect_url(url.clone()).await?;
+    let response = loop {
+        // Multipart bodies are streamed, and need to be re-created
+        // for every attempt, which also (re-)opens any file parts
+        let multipart = match &request.multipart {
+            Some(multipart) => Some((multipart.content_type(), multipart.to_body().await?)),
+            None => None,
+        };
+
+        let proxy = super::resolve_url_proxy(&lua, &url, &request.proxy)?;
+        let (stream, proxy_auth) = super::connect_http(&url, proxy.as_ref()).await?;
 
         let (mut sender, conn) = handshake(HyperIo::from(stream)).await.into_lua_err()?;
 
@@ -62,9 +76,26 @@ pub async fn send(mut request: Request, lua: Lua) -> LuaResult<Response> {
             let host = HeaderValue::from_str(host).unwrap();
             parts.headers.insert(HOST, host);
         }
+        jar.apply_to_headers(&url, &mut parts.headers);
+        if let Some(auth) = proxy_auth {
+            let auth = HeaderValue::from_str(&auth).into_lua_err()?;
+            parts.headers.insert(PROXY_AUTHORIZATION, auth);
+        }
 
-        let data = HyperRequest::from_parts(parts, Full::new(body.into_bytes()));
+        let body = if let Some((content_type, multipart)) = multipart {
+            let content_type = HeaderValue::from_str(&content_type).unwrap();
+            parts.headers.insert(CONTENT_TYPE, content_type);
+            parts
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from(multipart.len()));
+            Either::Right(multipart)
+        } else {
+            Either::Left(Full::new(body.into_bytes()))
+        };
+
+        let data = HyperRequest::from_parts(parts, body);
         let incoming = sender.send_request(data).await.into_lua_err()?;
+        jar.set_from_headers(&url, incoming.headers());
 
         if super::try_follow_redirect(&mut url, &mut request, &incoming)
             .map_err(LuaError::external)?
@@ -72,6 +103,10 @@ pub async fn send(mut request: Request, lua: Lua) -> LuaResult<Response> {
             continue;
         }
 
-        break Response::from_incoming(incoming, request.decompress).await;
-    }
+        break Response::from_incoming(incoming, request.decompress).await?;
+    };
+
+    jar.save_if_changed().await.into_lua_err()?;
+
+    Ok(response)
 }
@@ -17,7 +17,10 @@ use futures_rustls::{TlsConnector, TlsStream};
 use rustls_pki_types::ServerName;
 use url::Url;
 
-use crate::client::rustls::CLIENT_CONFIG;
+use crate::client::{
+    proxy::{Proxy, connect_tcp},
+    rustls::CLIENT_CONFIG,
+};
 
 /**
     Type alias for differentiating between a [`MaybeTlsStream`]
@@ -42,13 +45,15 @@ impl MaybeTlsStream {
     /**
         Connects to a host and port, additionally using TLS if specified.
 
+        If a proxy is given, the connection is tunneled through it.
+
         Using this constructor is likely unergonomic - prefer using
         [`MaybeTlsStream::connect_url`] instead, if possible.
 
         The given `host` must be a valid DNS name, when using TLS.
     */
-    pub async fn connect(host: &str, port: u16, tls: bool) -> Result<Self> {
-        let stream = TcpStream::connect((host, port)).await?;
+    pub async fn connect(host: &str, port: u16, tls: bool, proxy: Option<&Proxy>) -> Result<Self> {
+        let stream = connect_tcp(host, port, proxy).await?;
 
         let stream = if tls {
             let servname = ServerName::try_from(host).map_err(Error::other)?.to_owned();
@@ -66,8 +71,10 @@ impl MaybeTlsStream {
        Connects to the given URL.
 
        Automatically determines whether or not to use TLS based on the URL scheme.
+
+       If a proxy is given, the connection is tunneled through it.
     */
-    pub async fn connect_url(url: Url) -> Result<Self> {
+    pub async fn connect_url(url: Url, proxy: Option<&Proxy>) -> Result<Self> {
         let Some(host) = url.host() else {
             return Err(Error::other("unknown or missing host"));
         };
@@ -82,7 +89,7 @@ impl MaybeTlsStream {
         };
 
         let host = host.to_string();
-        Self::connect(&host, port, use_tls).await
+        Self::connect(&host, port, use_tls, proxy).await
     }
 
     /**
@@ -185,11 +192,13 @@ impl WsStream {
        Connects to the given URL.
 
        Automatically determines whether or not to use TLS based on the URL scheme.
+
+       If a proxy is given, the connection is tunneled through it.
     */
-    pub async fn connect_url(url: Url) -> Result<Self> {
+    pub async fn connect_url(url: Url, proxy: Option<&Proxy>) -> Result<Self> {
         let url_str = url.to_string();
 
-        let stream = MaybeTlsStream::connect_url(url).await?;
+        let stream = MaybeTlsStream::connect_url(url, proxy).await?;
         let (inner, _) = async_tungstenite::client_async(url_str, stream)
             .await
             .map_err(Error::other)?;
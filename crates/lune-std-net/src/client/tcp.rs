@@ -1,19 +1,22 @@
 use mlua::prelude::*;
 
-#[derive(Debug, Default, Clone, Copy)]
+use crate::client::proxy::ProxyConfig;
+
+#[derive(Debug, Default, Clone)]
 pub struct TcpConfig {
     pub tls: Option<bool>,
     pub ttl: Option<u32>,
+    pub proxy: ProxyConfig,
 }
 
 impl FromLua for TcpConfig {
-    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
         if let LuaValue::Nil = value {
             Ok(TcpConfig::default())
         } else if let LuaValue::Boolean(tls) = value {
             Ok(TcpConfig {
                 tls: Some(tls),
-                ttl: None,
+                ..Default::default()
             })
         } else if let LuaValue::Table(tab) = value {
             let mut this = TcpConfig::default();
@@ -24,6 +27,9 @@ impl FromLua for TcpConfig {
             if let Some(ttl) = tab.get::<Option<_>>("ttl")? {
                 this.ttl = Some(ttl);
             }
+            if let Some(proxy) = tab.get::<Option<LuaValue>>("proxy")? {
+                this.proxy = ProxyConfig::from_lua(proxy, lua)?;
+            }
 
             Ok(this)
         } else {
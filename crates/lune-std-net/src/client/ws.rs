@@ -0,0 +1,30 @@
+use mlua::prelude::*;
+
+use crate::client::proxy::ProxyConfig;
+
+#[derive(Debug, Default, Clone)]
+pub struct WsConfig {
+    pub proxy: ProxyConfig,
+}
+
+impl FromLua for WsConfig {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        if let LuaValue::Nil = value {
+            Ok(WsConfig::default())
+        } else if let LuaValue::Table(tab) = value {
+            let mut this = WsConfig::default();
+
+            if let Some(proxy) = tab.get::<Option<LuaValue>>("proxy")? {
+                this.proxy = ProxyConfig::from_lua(proxy, lua)?;
+            }
+
+            Ok(this)
+        } else {
+            Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: String::from("WsConfig"),
+                message: None,
+            })
+        }
+    }
+}
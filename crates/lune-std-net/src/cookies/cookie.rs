@@ -0,0 +1,197 @@
+use std::{
+    net::IpAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use url::Url;
+
+/**
+    A single cookie, as stored in a cookie jar.
+
+    Follows the storage model described in RFC 6265, where a cookie
+    is uniquely identified by its name, domain, and path.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    pub expires: Option<u64>,
+}
+
+impl Cookie {
+    /**
+        Parses a cookie from the value of a `Set-Cookie` header,
+        received as part of a response for the given URL.
+
+        Returns `None` if the header is malformed, or if the
+        cookie is not allowed to be set for the given URL.
+    */
+    pub fn parse(header: &str, url: &Url) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+
+        let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+        let (name, value) = pair.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url.path()),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+        let mut expires = None;
+        for attribute in attributes.split(';') {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let key = key.trim();
+            let value = value.trim();
+            if key.eq_ignore_ascii_case("expires") {
+                expires = parse_expires(value).or(expires);
+            } else if key.eq_ignore_ascii_case("max-age") {
+                max_age = value.parse::<i64>().ok().or(max_age);
+            } else if key.eq_ignore_ascii_case("domain") {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if !domain.is_empty() {
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+            } else if key.eq_ignore_ascii_case("path") {
+                if value.starts_with('/') {
+                    cookie.path = value.to_string();
+                }
+            } else if key.eq_ignore_ascii_case("secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("httponly") {
+                cookie.http_only = true;
+            }
+        }
+
+        // Max-Age takes precedence over Expires, and non-positive
+        // values mean that the cookie should expire immediately
+        cookie.expires = match max_age {
+            Some(age) if age <= 0 => Some(0),
+            Some(age) => Some(unix_now().saturating_add(age.unsigned_abs())),
+            None => expires,
+        };
+
+        // NOTE: Cookies may not be set for a public suffix such as "com" or "co.uk",
+        // unless the host is that exact suffix, in which case it is host-only instead
+        if !cookie.host_only && is_public_suffix(&cookie.domain) {
+            if cookie.domain == host {
+                cookie.host_only = true;
+            } else {
+                return None;
+            }
+        }
+
+        if !cookie.host_only && !domain_match(&host, &cookie.domain) {
+            return None;
+        }
+
+        Some(cookie)
+    }
+
+    /**
+        Returns `true` if the cookie has expired, and should be removed.
+    */
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /**
+        Returns `true` if the cookie should be sent with a request to the given URL.
+    */
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+
+        let secure_ok = !self.secure || matches!(url.scheme(), "https" | "wss");
+
+        domain_ok && secure_ok && path_match(url.path(), &self.path)
+    }
+
+    /**
+        Returns `true` if this cookie has the same identity
+        (name, domain, and path) as the other cookie.
+    */
+    pub fn same_identity(&self, other: &Self) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/**
+    Returns the current unix timestamp, in seconds.
+*/
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+fn parse_expires(value: &str) -> Option<u64> {
+    // Some servers use dashes in dates, such as "Wed, 21-Oct-2015 07:28:00 GMT"
+    let time = httpdate::parse_http_date(value)
+        .or_else(|_| httpdate::parse_http_date(&value.replace('-', " ")))
+        .ok()?;
+    Some(
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs(),
+    )
+}
+
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.parse::<IpAddr>().is_err()
+        && host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn is_public_suffix(domain: &str) -> bool {
+    // NOTE: Unknown top-level domains also count as public
+    // suffixes here, which rejects any single-label domain
+    psl::suffix_str(domain) == Some(domain)
+}
+
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    if request_path == cookie_path {
+        return true;
+    }
+    request_path.starts_with(cookie_path)
+        && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/'))
+}
+
+fn default_path(uri_path: &str) -> String {
+    if !uri_path.starts_with('/') {
+        return String::from("/");
+    }
+    match uri_path.rfind('/') {
+        Some(0) | None => String::from("/"),
+        Some(idx) => uri_path[..idx].to_string(),
+    }
+}
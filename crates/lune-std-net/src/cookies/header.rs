@@ -0,0 +1,25 @@
+use hyper::{HeaderMap, header::COOKIE};
+
+/**
+    Parses all name-value pairs from the `Cookie` header(s) in a request.
+
+    Pairs are returned in the order they appear, and any malformed pairs are skipped.
+*/
+pub fn parse_cookie_header(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            (!name.is_empty()).then(|| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,316 @@
+use std::{
+    fmt::Write as _,
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use hyper::{
+    HeaderMap,
+    header::{COOKIE, HeaderValue, SET_COOKIE},
+};
+
+use lune_utils::TableBuilder;
+use mlua::prelude::*;
+use url::Url;
+
+use super::cookie::{Cookie, unix_now};
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File\n";
+const NETSCAPE_HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+#[derive(Debug, Default)]
+struct CookieStore {
+    cookies: Vec<Cookie>,
+    path: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl CookieStore {
+    fn remove_expired(&mut self, now: u64) {
+        let len = self.cookies.len();
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+        if self.cookies.len() != len {
+            self.dirty = true;
+        }
+    }
+
+    fn insert(&mut self, cookie: Cookie) {
+        let existing = self.cookies.iter().position(|c| c.same_identity(&cookie));
+        if cookie.is_expired(unix_now()) {
+            // An already-expired cookie is how servers remove cookies
+            if let Some(idx) = existing {
+                self.cookies.remove(idx);
+            }
+        } else if let Some(idx) = existing {
+            // Replacing a cookie keeps its original creation order
+            self.cookies[idx] = cookie;
+        } else {
+            self.cookies.push(cookie);
+        }
+        self.dirty = true;
+    }
+}
+
+/**
+    A cookie jar, storing cookies received in responses and
+    attaching them to any matching requests that are sent.
+
+    Cookies follow the domain, path, expiry, and secure semantics
+    described in RFC 6265, and may optionally be persisted to a file
+    using the Netscape cookie file format, also used by `curl`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    inner: Arc<Mutex<CookieStore>>,
+}
+
+impl CookieJar {
+    /**
+        Creates a new cookie jar that is persisted to the given file.
+
+        Any cookies already present in the file will be loaded.
+
+        # Errors
+
+        Errors if the file exists, but could not be read.
+    */
+    pub fn with_path(path: PathBuf) -> io::Result<Self> {
+        let cookies = match std::fs::read_to_string(&path) {
+            Ok(contents) => parse_netscape(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut store = CookieStore {
+            cookies,
+            path: Some(path),
+            dirty: false,
+        };
+        store.remove_expired(unix_now());
+        Ok(Self {
+            inner: Arc::new(Mutex::new(store)),
+        })
+    }
+
+    /**
+        Stores a cookie from the value of a `Set-Cookie` header received for the given URL.
+
+        Returns `true` if the cookie was valid and stored, `false` otherwise.
+    */
+    pub fn set(&self, url: &Url, header: &str) -> bool {
+        if let Some(cookie) = Cookie::parse(header, url) {
+            self.inner.lock().unwrap().insert(cookie);
+            true
+        } else {
+            false
+        }
+    }
+
+    /**
+        Stores all cookies from `Set-Cookie` headers in a response received for the given URL.
+    */
+    pub fn set_from_headers(&self, url: &Url, headers: &HeaderMap) {
+        for header in headers.get_all(SET_COOKIE) {
+            if let Ok(header) = header.to_str() {
+                self.set(url, header);
+            }
+        }
+    }
+
+    /**
+        Returns all cookies that should be sent with a request to the given URL,
+        as name-value pairs, in the order they should appear in a `Cookie` header.
+    */
+    pub fn get(&self, url: &Url) -> Vec<(String, String)> {
+        let mut store = self.inner.lock().unwrap();
+        store.remove_expired(unix_now());
+
+        let mut matching = store
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url))
+            .collect::<Vec<_>>();
+
+        // Cookies with longer paths are listed first, and the sort being
+        // stable means cookies with equal paths keep their creation order
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+
+        matching
+            .into_iter()
+            .map(|cookie| (cookie.name.clone(), cookie.value.clone()))
+            .collect()
+    }
+
+    /**
+        Adds any cookies matching the given URL to the `Cookie` header,
+        appending them to any cookies that were already present.
+    */
+    pub fn apply_to_headers(&self, url: &Url, headers: &mut HeaderMap) {
+        let cookies = self.get(url);
+        if cookies.is_empty() {
+            return;
+        }
+
+        let mut header = headers
+            .get(COOKIE)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        for (name, value) in cookies {
+            if !header.is_empty() {
+                header.push_str("; ");
+            }
+            header.push_str(&name);
+            header.push('=');
+            header.push_str(&value);
+        }
+
+        if let Ok(value) = HeaderValue::from_str(&header) {
+            headers.insert(COOKIE, value);
+        }
+    }
+
+    /**
+        Removes all cookies from the jar.
+    */
+    pub fn clear(&self) {
+        let mut store = self.inner.lock().unwrap();
+        store.cookies.clear();
+        store.dirty = true;
+    }
+
+    /**
+        Saves the jar to the given path, or the path it was created with.
+
+        Does nothing if no path was given, and the jar was not created with a path.
+
+        # Errors
+
+        Errors if the file could not be written.
+    */
+    pub async fn save(&self, path: Option<PathBuf>) -> io::Result<()> {
+        let (path, contents) = {
+            let mut store = self.inner.lock().unwrap();
+            store.remove_expired(unix_now());
+            let Some(path) = path.or_else(|| store.path.clone()) else {
+                return Ok(());
+            };
+            store.dirty = false;
+            (path, format_netscape(&store.cookies))
+        };
+        blocking::unblock(move || std::fs::write(path, contents)).await
+    }
+
+    /**
+        Saves the jar to the path it was created with, if any cookies have changed.
+
+        # Errors
+
+        Errors if the file could not be written.
+    */
+    pub async fn save_if_changed(&self) -> io::Result<()> {
+        let changed = {
+            let store = self.inner.lock().unwrap();
+            store.dirty && store.path.is_some()
+        };
+        if changed {
+            self.save(None).await
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl LuaUserData for CookieJar {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("get", |lua, this, url: String| {
+            let url = url.parse::<Url>().into_lua_err()?;
+            let mut builder = TableBuilder::new(lua.clone())?;
+            // Earlier cookies are more specific, and take precedence over later ones
+            for (name, value) in this.get(&url).into_iter().rev() {
+                builder = builder.with_value(name, value)?;
+            }
+            builder.build_readonly()
+        });
+        methods.add_method("set", |_, this, (url, header): (String, String)| {
+            let url = url.parse::<Url>().into_lua_err()?;
+            Ok(this.set(&url, &header))
+        });
+        methods.add_method("clear", |_, this, ()| {
+            this.clear();
+            Ok(())
+        });
+        methods.add_async_method("save", |_, this, path: Option<String>| async move {
+            this.save(path.map(PathBuf::from)).await.into_lua_err()
+        });
+    }
+}
+
+fn format_netscape(cookies: &[Cookie]) -> String {
+    let mut contents = String::from(NETSCAPE_HEADER);
+    for cookie in cookies {
+        let http_only = if cookie.http_only {
+            NETSCAPE_HTTP_ONLY_PREFIX
+        } else {
+            ""
+        };
+        let domain_prefix = if cookie.host_only { "" } else { "." };
+        let bool_str = |b: bool| if b { "TRUE" } else { "FALSE" };
+        writeln!(
+            contents,
+            "{http_only}{domain_prefix}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            cookie.domain,
+            bool_str(!cookie.host_only),
+            cookie.path,
+            bool_str(cookie.secure),
+            cookie.expires.unwrap_or(0),
+            cookie.name,
+            cookie.value,
+        )
+        .unwrap();
+    }
+    contents
+}
+
+fn parse_netscape(contents: &str) -> Vec<Cookie> {
+    let mut cookies = Vec::new();
+    for line in contents.lines() {
+        let (line, http_only) = match line.strip_prefix(NETSCAPE_HTTP_ONLY_PREFIX) {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let [
+            domain,
+            include_subdomains,
+            path,
+            secure,
+            expires,
+            name,
+            value,
+        ] = fields[..]
+        else {
+            continue;
+        };
+        let Ok(expires) = expires.parse::<u64>() else {
+            continue;
+        };
+
+        cookies.push(Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            host_only: !include_subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            // Session cookies are stored with an expiry of zero
+            expires: (expires != 0).then_some(expires),
+        });
+    }
+    cookies
+}
@@ -0,0 +1,9 @@
+#![allow(unused_imports)]
+
+mod cookie;
+mod header;
+mod jar;
+
+pub use self::cookie::Cookie;
+pub use self::header::parse_cookie_header;
+pub use self::jar::CookieJar;
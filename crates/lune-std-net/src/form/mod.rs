@@ -0,0 +1,9 @@
+#![allow(unused_imports)]
+
+mod multipart;
+mod multipart_body;
+mod parse;
+
+pub use self::multipart::{Multipart, MultipartPart, MultipartSource};
+pub use self::multipart_body::MultipartBody;
+pub use self::parse::{FormData, FormFile};
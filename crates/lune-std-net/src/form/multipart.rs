@@ -0,0 +1,226 @@
+use std::{fs::File, path::PathBuf};
+
+use hyper::{body::Bytes, header::HeaderValue};
+use mlua::prelude::*;
+
+use crate::{body::ReadableBody, form::multipart_body::MultipartBody};
+
+const BOUNDARY_PREFIX: &str = "----LuneFormBoundary";
+const BOUNDARY_RANDOM_LEN: usize = 24;
+
+const DEFAULT_FILE_CONTENT_TYPE: &str = "application/octet-stream";
+
+/**
+    The source of data for a single part in a multipart body.
+*/
+#[derive(Debug, Clone)]
+pub enum MultipartSource {
+    Body(ReadableBody),
+    File(PathBuf),
+}
+
+/**
+    A single part (field or file) in a multipart body.
+*/
+#[derive(Debug, Clone)]
+pub struct MultipartPart {
+    pub name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub source: MultipartSource,
+}
+
+impl MultipartPart {
+    fn header_bytes(&self, boundary: &str) -> Bytes {
+        let mut header = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"",
+            escape_param(&self.name)
+        );
+        if let Some(filename) = &self.filename {
+            header.push_str("; filename=\"");
+            header.push_str(&escape_param(filename));
+            header.push('"');
+        }
+        header.push_str("\r\n");
+        if let Some(content_type) = &self.content_type {
+            header.push_str("Content-Type: ");
+            header.push_str(content_type);
+            header.push_str("\r\n");
+        }
+        header.push_str("\r\n");
+        Bytes::from(header)
+    }
+}
+
+impl FromLua for MultipartPart {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "MultipartPart".to_string(),
+                message: Some(format!(
+                    "Invalid multipart part - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let name = tab.get::<LuaString>("name")?.to_str()?.to_string();
+        let mut filename = tab.get::<Option<String>>("filename")?;
+        let mut content_type = tab.get::<Option<String>>("contentType")?;
+
+        // NOTE: The content type is written as-is into the headers of the part,
+        // so it must be a valid header value, and not contain any line breaks
+        if let Some(content_type) = &content_type
+            && HeaderValue::from_str(content_type).is_err()
+        {
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid multipart part '{name}' - 'contentType' is not a valid header value"
+            )));
+        }
+
+        let value = tab.get::<LuaValue>("value")?;
+        let path = tab.get::<Option<String>>("path")?;
+
+        let source = match (value, path) {
+            (LuaValue::Nil, None) => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid multipart part '{name}' - expected either 'value' or 'path'"
+                )));
+            }
+            (LuaValue::Nil, Some(path)) => {
+                let path = PathBuf::from(path);
+                if filename.is_none() {
+                    filename = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string());
+                }
+                MultipartSource::File(path)
+            }
+            (value, None) => {
+                let body = ReadableBody::from_lua(value, lua)?;
+                MultipartSource::Body(body)
+            }
+            (_, Some(_)) => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid multipart part '{name}' - 'value' and 'path' are mutually exclusive"
+                )));
+            }
+        };
+
+        if filename.is_some() && content_type.is_none() {
+            content_type = Some(DEFAULT_FILE_CONTENT_TYPE.to_string());
+        }
+
+        Ok(Self {
+            name,
+            filename,
+            content_type,
+            source,
+        })
+    }
+}
+
+/**
+    A description of a `multipart/form-data` body.
+
+    Files are not opened or read until the body is created using
+    [`Multipart::to_body`], which may be done once per request attempt.
+*/
+#[derive(Debug, Clone)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<MultipartPart>,
+}
+
+impl Multipart {
+    /**
+        Creates a new multipart body description with a random boundary.
+    */
+    pub fn new(parts: Vec<MultipartPart>) -> Self {
+        let random = (0..BOUNDARY_RANDOM_LEN)
+            .map(|_| fastrand::alphanumeric())
+            .collect::<String>();
+        Self {
+            boundary: format!("{BOUNDARY_PREFIX}{random}"),
+            parts,
+        }
+    }
+
+    /**
+        Returns the value to use for the `Content-Type` header.
+    */
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /**
+        Creates a streaming body for this multipart description.
+
+        Any file parts are opened here, and will be read in chunks as the body is sent.
+
+        # Errors
+
+        Errors if any file part could not be opened or its metadata could not be read.
+    */
+    pub async fn to_body(&self) -> LuaResult<MultipartBody> {
+        let mut body = MultipartBody::new();
+
+        for part in &self.parts {
+            body.push_bytes(part.header_bytes(&self.boundary));
+            match &part.source {
+                MultipartSource::Body(b) => body.push_bytes(b.clone().into_bytes()),
+                MultipartSource::File(path) => {
+                    let path = path.clone();
+                    let (file, len) = blocking::unblock(move || {
+                        let file = File::open(&path)?;
+                        let len = file.metadata()?.len();
+                        Ok::<_, std::io::Error>((file, len))
+                    })
+                    .await
+                    .map_err(|e| {
+                        LuaError::RuntimeError(format!(
+                            "Failed to open file for multipart part '{}' - {e}",
+                            part.name
+                        ))
+                    })?;
+                    body.push_file(file, len);
+                }
+            }
+            body.push_bytes(Bytes::from_static(b"\r\n"));
+        }
+
+        body.push_bytes(Bytes::from(format!("--{}--\r\n", self.boundary)));
+
+        Ok(body)
+    }
+}
+
+impl FromLua for Multipart {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Multipart".to_string(),
+                message: Some(format!(
+                    "Invalid multipart - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let mut parts = Vec::new();
+        for value in tab.sequence_values::<LuaValue>() {
+            parts.push(MultipartPart::from_lua(value?, lua)?);
+        }
+
+        Ok(Self::new(parts))
+    }
+}
+
+fn escape_param(s: &str) -> String {
+    // Same escaping as browsers use for field names and filenames
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
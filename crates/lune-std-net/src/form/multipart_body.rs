@@ -0,0 +1,105 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use blocking::Unblock;
+use futures_lite::{AsyncRead, ready};
+use hyper::body::{Body, Bytes, Frame, SizeHint};
+
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+enum Segment {
+    Bytes(Bytes),
+    File(Unblock<File>),
+}
+
+/**
+    A streaming `multipart/form-data` body.
+
+    In-memory parts are sent as-is, while file parts are read in
+    chunks on the blocking thread pool, and never fully buffered.
+*/
+pub struct MultipartBody {
+    segments: VecDeque<Segment>,
+    length: u64,
+    buffer: Vec<u8>,
+}
+
+impl MultipartBody {
+    pub(super) fn new() -> Self {
+        Self {
+            segments: VecDeque::new(),
+            length: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub(super) fn push_bytes(&mut self, bytes: Bytes) {
+        if !bytes.is_empty() {
+            self.length += bytes.len() as u64;
+            self.segments.push_back(Segment::Bytes(bytes));
+        }
+    }
+
+    pub(super) fn push_file(&mut self, file: File, len: u64) {
+        if len > 0 {
+            self.length += len;
+            self.segments.push_back(Segment::File(Unblock::new(file)));
+        }
+    }
+
+    /**
+        Returns the total length of the body, in bytes.
+    */
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+}
+
+impl Body for MultipartBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        loop {
+            match this.segments.front_mut() {
+                None => return Poll::Ready(None),
+                Some(Segment::Bytes(_)) => {
+                    let Some(Segment::Bytes(bytes)) = this.segments.pop_front() else {
+                        unreachable!()
+                    };
+                    return Poll::Ready(Some(Ok(Frame::data(bytes))));
+                }
+                Some(Segment::File(file)) => {
+                    this.buffer.resize(FILE_CHUNK_SIZE, 0);
+                    let n = match ready!(Pin::new(file).poll_read(cx, &mut this.buffer)) {
+                        Ok(n) => n,
+                        Err(e) => return Poll::Ready(Some(Err(e))),
+                    };
+                    if n == 0 {
+                        this.segments.pop_front();
+                        continue;
+                    }
+                    let chunk = Bytes::copy_from_slice(&this.buffer[..n]);
+                    return Poll::Ready(Some(Ok(Frame::data(chunk))));
+                }
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.length)
+    }
+}
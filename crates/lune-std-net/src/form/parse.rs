@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use bstr::ByteSlice;
+use hyper::{HeaderMap, header::CONTENT_TYPE};
+
+use lune_utils::TableBuilder;
+use mlua::prelude::*;
+
+const MIME_URLENCODED: &str = "application/x-www-form-urlencoded";
+const MIME_MULTIPART: &str = "multipart/form-data";
+
+/**
+    A single file that was uploaded as part of a `multipart/form-data` body.
+*/
+#[derive(Debug, Clone)]
+pub struct FormFile {
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl IntoLua for FormFile {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        TableBuilder::new(lua.clone())?
+            .with_value("filename", self.filename)?
+            .with_value("contentType", self.content_type)?
+            .with_value("body", lua.create_string(self.body)?)?
+            .build_readonly()?
+            .into_lua(lua)
+    }
+}
+
+/**
+    Parsed contents of a form body, either `application/x-www-form-urlencoded`
+    or `multipart/form-data`, grouped by field name in order of appearance.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct FormData {
+    pub fields: HashMap<String, Vec<String>>,
+    pub files: HashMap<String, Vec<FormFile>>,
+}
+
+impl FormData {
+    /**
+        Parses form data from the given headers and body.
+
+        Returns `None` if the body is not a form, as determined by the `Content-Type` header.
+
+        # Errors
+
+        Errors if the body is a multipart body, but is malformed.
+    */
+    pub fn parse(headers: &HeaderMap, body: &[u8]) -> LuaResult<Option<Self>> {
+        let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+            return Ok(None);
+        };
+
+        let (mime, params) = split_header_value(content_type);
+        if mime.eq_ignore_ascii_case(MIME_URLENCODED) {
+            Ok(Some(Self::parse_urlencoded(body)))
+        } else if mime.eq_ignore_ascii_case(MIME_MULTIPART) {
+            let boundary = params
+                .into_iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+                .map(|(_, value)| value)
+                .ok_or_else(|| {
+                    LuaError::RuntimeError(
+                        "Invalid multipart body - missing boundary in content type".to_string(),
+                    )
+                })?;
+            Self::parse_multipart(body, &boundary).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_urlencoded(body: &[u8]) -> Self {
+        let mut this = Self::default();
+        for (key, value) in form_urlencoded::parse(body) {
+            this.fields
+                .entry(key.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+        this
+    }
+
+    fn parse_multipart(body: &[u8], boundary: &str) -> LuaResult<Self> {
+        let malformed =
+            |reason: &str| LuaError::RuntimeError(format!("Invalid multipart body - {reason}"));
+
+        let delimiter = format!("--{boundary}");
+        let delimiter = delimiter.as_bytes();
+        let next_delimiter = format!("\r\n--{boundary}");
+        let next_delimiter = next_delimiter.as_bytes();
+
+        let mut this = Self::default();
+
+        // Skip the preamble, if any, to get to the first delimiter
+        let start = body
+            .find(delimiter)
+            .ok_or_else(|| malformed("missing boundary"))?;
+        let mut rest = &body[start + delimiter.len()..];
+
+        loop {
+            // A delimiter followed by two dashes marks the end of the body
+            if rest.starts_with(b"--") {
+                break;
+            }
+            rest = rest.trim_start_with(|c| c == ' ' || c == '\t');
+            rest = rest
+                .strip_prefix(b"\r\n")
+                .ok_or_else(|| malformed("expected newline after boundary"))?;
+
+            let headers_end = if rest.starts_with(b"\r\n") {
+                (0, 2)
+            } else {
+                rest.find(b"\r\n\r\n")
+                    .map(|idx| (idx, idx + 4))
+                    .ok_or_else(|| malformed("unterminated part headers"))?
+            };
+            let header_block = &rest[..headers_end.0];
+            rest = &rest[headers_end.1..];
+
+            let content_end = rest
+                .find(next_delimiter)
+                .ok_or_else(|| malformed("unterminated part"))?;
+            let content = &rest[..content_end];
+            rest = &rest[content_end + next_delimiter.len()..];
+
+            let mut name = None;
+            let mut filename = None;
+            let mut content_type = None;
+            for line in header_block.lines() {
+                let Some((key, value)) = line.split_once_str(":") else {
+                    continue;
+                };
+                let key = key.to_str_lossy();
+                let value = value.to_str_lossy();
+                let value = value.trim();
+                if key.trim().eq_ignore_ascii_case("content-disposition") {
+                    let (_, params) = split_header_value(value);
+                    for (key, value) in params {
+                        if key.eq_ignore_ascii_case("name") {
+                            name = Some(value);
+                        } else if key.eq_ignore_ascii_case("filename") {
+                            filename = Some(value);
+                        }
+                    }
+                } else if key.trim().eq_ignore_ascii_case("content-type") {
+                    content_type = Some(value.to_string());
+                }
+            }
+
+            let name = name.ok_or_else(|| malformed("part is missing a field name"))?;
+            if let Some(filename) = filename {
+                this.files.entry(name).or_default().push(FormFile {
+                    filename,
+                    content_type,
+                    body: content.to_vec(),
+                });
+            } else {
+                this.fields
+                    .entry(name)
+                    .or_default()
+                    .push(content.to_str_lossy().to_string());
+            }
+        }
+
+        Ok(this)
+    }
+
+    /**
+        Converts the parsed files into a Lua table, using the same
+        single-value-or-array convention as headers and query maps.
+    */
+    pub fn files_into_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        let mut builder = TableBuilder::new(lua.clone())?;
+        for (name, mut files) in self.files {
+            if files.len() == 1 {
+                builder = builder.with_value(name, files.pop().unwrap())?;
+            } else {
+                let files = TableBuilder::new(lua.clone())?
+                    .with_sequential_values(files)?
+                    .build_readonly()?;
+                builder = builder.with_value(name, files)?;
+            }
+        }
+        builder.build_readonly()
+    }
+}
+
+/**
+    Splits a header value such as `form-data; name="field"` into
+    its main value and a list of its (unquoted) parameters.
+*/
+fn split_header_value(value: &str) -> (&str, Vec<(String, String)>) {
+    let (main, mut rest) = value.split_once(';').unwrap_or((value, ""));
+
+    let mut params = Vec::new();
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        if rest.is_empty() {
+            break;
+        }
+
+        let key_end = rest.find(['=', ';']).unwrap_or(rest.len());
+        let key = rest[..key_end].trim().to_string();
+        let Some(after_key) = rest[key_end..].strip_prefix('=') else {
+            rest = &rest[key_end..];
+            params.push((key, String::new()));
+            continue;
+        };
+        let after_key = after_key.trim_start();
+
+        let value = if let Some(quoted) = after_key.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((idx, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        end = idx + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            rest = &quoted[end..];
+            value
+        } else {
+            let (value, after_value) = after_key.split_once(';').unwrap_or((after_key, ""));
+            rest = after_value;
+            value.trim().to_string()
+        };
+
+        params.push((key, value));
+    }
+
+    (main.trim(), params)
+}
@@ -5,6 +5,8 @@ use mlua::prelude::*;
 
 pub(crate) mod body;
 pub(crate) mod client;
+pub(crate) mod cookies;
+pub(crate) mod form;
 pub(crate) mod server;
 pub(crate) mod shared;
 pub(crate) mod url;
@@ -12,9 +14,11 @@ pub(crate) mod url;
 use crate::shared::{hyper::HyperExecutor, tcp::Tcp};
 
 use self::{
-    client::{stream::WsStream, tcp::TcpConfig},
+    client::{stream::WsStream, tcp::TcpConfig, ws::WsConfig},
+    cookies::CookieJar,
     server::config::ServeConfig,
     shared::{request::Request, response::Response, websocket::Websocket},
+    url::UrlParts,
 };
 
 pub use self::client::fetch;
@@ -42,6 +46,7 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     let submodule_http = TableBuilder::new(lua.clone())?
         .with_async_function("request", net_http_request)?
         .with_async_function("serve", net_http_serve)?
+        .with_function("cookieJar", net_http_cookie_jar)?
         .build_readonly()?;
 
     let submodule_tcp = TableBuilder::new(lua.clone())?
@@ -52,15 +57,25 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_async_function("connect", net_ws_connect)?
         .build_readonly()?;
 
+    let submodule_url = TableBuilder::new(lua.clone())?
+        .with_function("parse", net_url_parse)?
+        .with_function("build", net_url_build)?
+        .with_function("resolve", net_url_resolve)?
+        .with_function("encode", net_url_encode)?
+        .with_function("decode", net_url_decode)?
+        .build_readonly()?;
+
     TableBuilder::new(lua)?
         .with_async_function("request", net_http_request)?
         .with_async_function("socket", net_ws_connect)?
         .with_async_function("serve", net_http_serve)?
+        .with_function("cookieJar", net_http_cookie_jar)?
         .with_function("urlEncode", net_url_encode)?
         .with_function("urlDecode", net_url_decode)?
         .with_value("http", submodule_http)?
         .with_value("tcp", submodule_tcp)?
         .with_value("ws", submodule_ws)?
+        .with_value("url", submodule_url)?
         .build_readonly()
 }
 
@@ -74,13 +89,26 @@ async fn net_http_serve(lua: Lua, (port, config): (u16, ServeConfig)) -> LuaResu
         .into_lua_table(lua)
 }
 
-async fn net_tcp_connect(_: Lua, (host, port, config): (String, u16, TcpConfig)) -> LuaResult<Tcp> {
-    self::client::connect_tcp(host, port, config).await
+fn net_http_cookie_jar(_: &Lua, path: Option<String>) -> LuaResult<CookieJar> {
+    match path {
+        Some(path) => CookieJar::with_path(path.into()).into_lua_err(),
+        None => Ok(CookieJar::default()),
+    }
 }
 
-async fn net_ws_connect(_: Lua, url: String) -> LuaResult<Websocket<WsStream>> {
+async fn net_tcp_connect(
+    lua: Lua,
+    (host, port, config): (String, u16, TcpConfig),
+) -> LuaResult<Tcp> {
+    self::client::connect_tcp(&lua, host, port, config).await
+}
+
+async fn net_ws_connect(
+    lua: Lua,
+    (url, config): (String, WsConfig),
+) -> LuaResult<Websocket<WsStream>> {
     let url = url.parse().into_lua_err()?;
-    self::client::connect_ws(url).await
+    self::client::connect_ws(&lua, url, config).await
 }
 
 fn net_url_encode(
@@ -100,3 +128,16 @@ fn net_url_decode(
     let bytes = self::url::decode(lua_string, as_binary)?;
     lua.create_string(bytes)
 }
+
+fn net_url_parse(lua: &Lua, (url, base): (String, Option<String>)) -> LuaResult<LuaTable> {
+    let url = self::url::parse(&url, base.as_deref())?;
+    UrlParts::from(&url).into_lua_table(lua)
+}
+
+fn net_url_build(_: &Lua, parts: UrlParts) -> LuaResult<String> {
+    Ok(parts.to_url()?.to_string())
+}
+
+fn net_url_resolve(_: &Lua, (base, url): (String, String)) -> LuaResult<String> {
+    Ok(self::url::parse(&url, Some(&base))?.to_string())
+}
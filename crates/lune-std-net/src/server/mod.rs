@@ -5,7 +5,7 @@ use futures_lite::pin;
 use hyper::server::conn::http1::Builder as Http1Builder;
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::LuaSpawnExt;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
 
 use crate::{
     server::{config::ServeConfig, handle::ServeHandle, service::Service},
@@ -24,6 +24,8 @@ pub mod upgrade;
     Starts an HTTP server using the given port and configuration.
 
     Returns a `ServeHandle` that can be used to gracefully stop the server.
+
+    Handler threads inherit the context of the thread that started the server.
 */
 pub async fn serve(lua: Lua, port: u16, config: ServeConfig) -> LuaResult<ServeHandle> {
     let address = SocketAddr::from((config.address, port));
@@ -31,6 +33,7 @@ pub async fn serve(lua: Lua, port: u16, config: ServeConfig) -> LuaResult<ServeH
         lua: lua.clone(),
         address,
         config,
+        context: lua.current_context(),
     };
 
     let listener = TcpListener::bind(address).await?;
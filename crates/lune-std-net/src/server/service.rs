@@ -7,7 +7,7 @@ use hyper::{
 };
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+use mlua_luau_scheduler::{Context, LuaSchedulerExt, LuaSpawnExt};
 
 use crate::{
     body::ReadableBody,
@@ -23,6 +23,7 @@ pub(super) struct Service {
     pub(super) lua: Lua,
     pub(super) address: SocketAddr, // NOTE: This must be the remote address of the connected client
     pub(super) config: ServeConfig,
+    pub(super) context: Context, // NOTE: This is inherited by all handler threads
 }
 
 impl HyperService<HyperRequest<Incoming>> for Service {
@@ -35,6 +36,7 @@ impl HyperService<HyperRequest<Incoming>> for Service {
             && let Some(handler) = self.config.handle_web_socket.clone()
         {
             let lua = self.lua.clone();
+            let context = self.context.clone();
             return Box::pin(async move {
                 let response = match make_upgrade_response(&req) {
                     Ok(res) => res,
@@ -49,7 +51,7 @@ impl HyperService<HyperRequest<Incoming>> for Service {
                 lua.spawn_local({
                     let lua = lua.clone();
                     async move {
-                        if let Err(_err) = handle_websocket(lua, handler, req).await {
+                        if let Err(_err) = handle_websocket(lua, handler, context, req).await {
                             // TODO: Propagate the error somehow?
                         }
                     }
@@ -62,8 +64,9 @@ impl HyperService<HyperRequest<Incoming>> for Service {
         let lua = self.lua.clone();
         let address = self.address;
         let handler = self.config.handle_request.clone();
+        let context = self.context.clone();
         Box::pin(async move {
-            match handle_request(lua, handler, req, address).await {
+            match handle_request(lua, handler, context, req, address).await {
                 Ok(response) => Ok(response),
                 Err(_err) => {
                     // TODO: Propagate the error somehow?
@@ -80,6 +83,7 @@ impl HyperService<HyperRequest<Incoming>> for Service {
 async fn handle_request(
     lua: Lua,
     handler: LuaFunction,
+    context: Context,
     request: HyperRequest<Incoming>,
     address: SocketAddr,
 ) -> LuaResult<HyperResponse<ReadableBody>> {
@@ -87,7 +91,10 @@ async fn handle_request(
         .await?
         .with_address(address);
 
-    let thread_id = lua.push_thread_back(handler, request)?;
+    let thread = lua.create_thread(handler)?;
+    lua.set_thread_context(&thread, context)?;
+
+    let thread_id = lua.push_thread_back(thread, request)?;
     lua.track_thread(thread_id);
     lua.wait_for_thread(thread_id).await;
 
@@ -102,6 +109,7 @@ async fn handle_request(
 async fn handle_websocket(
     lua: Lua,
     handler: LuaFunction,
+    context: Context,
     request: HyperRequest<Incoming>,
 ) -> LuaResult<()> {
     let upgraded = hyper::upgrade::on(request).await.into_lua_err()?;
@@ -110,7 +118,10 @@ async fn handle_websocket(
         WebSocketStream::from_raw_socket(HyperIo::from(upgraded), Role::Server, None).await;
 
     let websocket = Websocket::from(stream);
-    lua.push_thread_back(handler, websocket)?;
+
+    let thread = lua.create_thread(handler)?;
+    lua.set_thread_context(&thread, context)?;
+    lua.push_thread_back(thread, websocket)?;
 
     Ok(())
 }
@@ -1,10 +1,14 @@
+use std::collections::{HashMap, HashSet};
+
 use hyper::{
     HeaderMap, Method,
-    header::{HeaderName, HeaderValue},
+    header::{COOKIE, HeaderName, HeaderValue},
 };
 
 use mlua::prelude::*;
 
+use crate::{cookies::CookieJar, shared::headers::hash_map_to_table};
+
 pub fn lua_value_to_method(value: &LuaValue) -> LuaResult<Method> {
     match value {
         LuaValue::Nil => Ok(Method::GET),
@@ -39,3 +43,159 @@ pub fn lua_table_to_header_map(table: &LuaTable) -> LuaResult<HeaderMap> {
 
     Ok(headers)
 }
+
+/**
+    Weakly keyed table of query tables created by Lune, to arrays of their keys.
+
+    Lua tables do not keep the order of their keys, so the order of a parsed query
+    is stored here instead, to build it back in the same order as it was parsed in.
+*/
+struct QueryOrder(LuaTable);
+
+fn query_order(lua: &Lua) -> LuaResult<LuaTable> {
+    if let Some(order) = lua.app_data_ref::<QueryOrder>() {
+        return Ok(order.0.clone());
+    }
+    let order = lua.create_table()?;
+    let meta = lua.create_table()?;
+    meta.set("__mode", "k")?;
+    order.set_metatable(Some(meta))?;
+    lua.set_app_data(QueryOrder(order.clone()));
+    Ok(order)
+}
+
+/**
+    Converts a list of query key-value pairs into a Lua table of query parameters.
+
+    Values for repeated keys are collected into arrays, and the order of the
+    keys is remembered for when the table is converted back into pairs.
+*/
+pub fn query_pairs_to_lua_table(
+    lua: &Lua,
+    pairs: impl IntoIterator<Item = (String, String)>,
+) -> LuaResult<LuaTable> {
+    let mut indices = HashMap::<String, usize>::new();
+    let mut query = Vec::<(String, Vec<String>)>::new();
+    for (key, value) in pairs {
+        if let Some(&index) = indices.get(&key) {
+            query[index].1.push(value);
+        } else {
+            indices.insert(key.clone(), query.len());
+            query.push((key, vec![value]));
+        }
+    }
+
+    let keys = lua.create_sequence_from(query.iter().map(|(key, _)| key.as_str()))?;
+    let table = hash_map_to_table(lua, query, false)?;
+    query_order(lua)?.raw_set(&table, keys)?;
+
+    Ok(table)
+}
+
+/**
+    Converts a Lua table of query parameters into a list of key-value pairs.
+
+    Values may either be strings, or arrays of strings for repeated keys,
+    and numbers are converted to strings, same as for Lua string coercion.
+
+    Keys of tables created using [`query_pairs_to_lua_table`] come first,
+    in their original order, followed by any other keys in the table.
+*/
+pub fn lua_table_to_query_pairs(lua: &Lua, table: &LuaTable) -> LuaResult<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    let mut ordered = HashSet::new();
+
+    if let Some(keys) = query_order(lua)?.raw_get::<Option<LuaTable>>(table)? {
+        for key in keys.sequence_values::<LuaString>() {
+            let key = key?;
+            let value = table.raw_get::<LuaValue>(&key)?;
+            if !value.is_nil() {
+                let key = key.to_str()?.to_string();
+                push_query_value(lua, &mut pairs, &key, value)?;
+                ordered.insert(key);
+            }
+        }
+    }
+
+    for pair in table.pairs::<LuaString, LuaValue>() {
+        let (key, value) = pair?;
+        let key = key.to_str()?.to_string();
+        if !ordered.contains(&key) {
+            push_query_value(lua, &mut pairs, &key, value)?;
+        }
+    }
+
+    Ok(pairs)
+}
+
+fn push_query_value(
+    lua: &Lua,
+    pairs: &mut Vec<(String, String)>,
+    key: &str,
+    value: LuaValue,
+) -> LuaResult<()> {
+    match value {
+        LuaValue::String(_) | LuaValue::Integer(_) | LuaValue::Number(_) => {
+            let value = LuaString::from_lua(value, lua)?;
+            pairs.push((key.to_string(), value.to_str()?.to_string()));
+        }
+        LuaValue::Table(t) => {
+            for value in t.sequence_values::<LuaString>() {
+                pairs.push((key.to_string(), value?.to_str()?.to_string()));
+            }
+        }
+        v => {
+            return Err(LuaError::FromLuaConversionError {
+                from: v.type_name(),
+                to: "QueryMap".to_string(),
+                message: Some(format!(
+                    "Invalid query value for '{key}' - expected string or array of strings, got {}",
+                    v.type_name()
+                )),
+            });
+        }
+    }
+    Ok(())
+}
+
+/**
+    Converts a Lua value into an optional cookie jar.
+
+    Plain tables of name-value pairs are instead added directly to
+    the `Cookie` header in the given header map, and return `None`.
+*/
+pub fn lua_value_to_cookie_jar(
+    value: LuaValue,
+    headers: &mut HeaderMap,
+) -> LuaResult<Option<CookieJar>> {
+    match value {
+        LuaValue::Nil => Ok(None),
+        LuaValue::UserData(ud) if ud.is::<CookieJar>() => {
+            Ok(Some(ud.borrow::<CookieJar>()?.clone()))
+        }
+        LuaValue::Table(t) => {
+            let mut pairs = headers
+                .get(COOKIE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| vec![v.to_string()])
+                .unwrap_or_default();
+            for pair in t.pairs::<LuaString, LuaString>() {
+                let (name, value) = pair?;
+                pairs.push(format!("{}={}", name.to_str()?, value.to_str()?));
+            }
+            if !pairs.is_empty() {
+                let header = HeaderValue::from_str(&pairs.join("; ")).into_lua_err()?;
+                headers.insert(COOKIE, header);
+            }
+            Ok(None)
+        }
+        v => Err(LuaError::FromLuaConversionError {
+            from: v.type_name(),
+            to: "CookieJar".to_string(),
+            message: Some(format!(
+                "Invalid cookies - expected table or CookieJar, got {}",
+                v.type_name()
+            )),
+        }),
+    }
+}
@@ -4,29 +4,40 @@ use url::Url;
 
 use hyper::{HeaderMap, Method, Request as HyperRequest, body::Incoming};
 
+use lune_utils::TableBuilder;
 use mlua::prelude::*;
 
 use crate::{
     body::{ReadableBody, handle_incoming_body},
+    client::proxy::ProxyConfig,
+    cookies::{CookieJar, parse_cookie_header},
+    form::{FormData, Multipart},
     shared::{
         headers::{hash_map_to_table, header_map_to_table},
-        lua::{lua_table_to_header_map, lua_value_to_method},
+        lua::{
+            lua_table_to_header_map, lua_table_to_query_pairs, lua_value_to_cookie_jar,
+            lua_value_to_method,
+        },
     },
 };
 
 #[derive(Debug, Clone)]
 pub struct RequestOptions {
     pub decompress: bool,
+    pub proxy: ProxyConfig,
 }
 
 impl Default for RequestOptions {
     fn default() -> Self {
-        Self { decompress: true }
+        Self {
+            decompress: true,
+            proxy: ProxyConfig::default(),
+        }
     }
 }
 
 impl FromLua for RequestOptions {
-    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
         if let LuaValue::Nil = value {
             // Nil means default options
             Ok(Self::default())
@@ -38,7 +49,13 @@ impl FromLua for RequestOptions {
                     "Invalid option value for 'decompress' in request options".to_string(),
                 )),
             }?;
-            Ok(Self { decompress })
+            let proxy = match tab.get::<LuaValue>("proxy") {
+                Ok(proxy) => ProxyConfig::from_lua(proxy, lua),
+                Err(_) => Err(LuaError::RuntimeError(
+                    "Invalid option value for 'proxy' in request options".to_string(),
+                )),
+            }?;
+            Ok(Self { decompress, proxy })
         } else {
             // Anything else is invalid
             Err(LuaError::FromLuaConversionError {
@@ -59,6 +76,9 @@ pub struct Request {
     pub(crate) address: Option<SocketAddr>,
     pub(crate) redirects: Option<usize>,
     pub(crate) decompress: bool,
+    pub(crate) multipart: Option<Multipart>,
+    pub(crate) cookies: Option<CookieJar>,
+    pub(crate) proxy: ProxyConfig,
 }
 
 impl Request {
@@ -78,6 +98,9 @@ impl Request {
             address: None,
             redirects: None,
             decompress,
+            multipart: None,
+            cookies: None,
+            proxy: ProxyConfig::default(),
         })
     }
 
@@ -139,6 +162,25 @@ impl Request {
         self.inner.body().as_slice()
     }
 
+    /**
+        Returns the cookies sent with the request.
+    */
+    pub fn cookies(&self) -> Vec<(String, String)> {
+        parse_cookie_header(self.headers())
+    }
+
+    /**
+        Returns the parsed form data of the request, if the
+        body is a urlencoded or multipart form.
+
+        # Errors
+
+        Errors if the body is a multipart form, but is malformed.
+    */
+    pub fn form(&self) -> LuaResult<Option<FormData>> {
+        FormData::parse(self.headers(), self.body())
+    }
+
     /**
         Clones the inner `hyper` request.
     */
@@ -164,6 +206,9 @@ impl<B: Into<ReadableBody>> From<HyperRequest<B>> for Request {
             address: None,
             redirects: None,
             decompress: false,
+            multipart: None,
+            cookies: None,
+            proxy: ProxyConfig::default(),
         }
     }
 }
@@ -184,6 +229,9 @@ impl FromLua for Request {
                 address: None,
                 redirects: None,
                 decompress: RequestOptions::default().decompress,
+                multipart: None,
+                cookies: None,
+                proxy: ProxyConfig::default(),
             })
         } else if let LuaValue::Table(tab) = value {
             // If we got a table we are able to configure the
@@ -197,26 +245,39 @@ impl FromLua for Request {
             let url = tab.get::<LuaString>("url")?;
             let mut url = url.to_str()?.parse::<Url>().into_lua_err()?;
             if let Some(t) = tab.get::<Option<LuaTable>>("query")? {
-                let mut query = url.query_pairs_mut();
-                for pair in t.pairs::<LuaString, LuaString>() {
-                    let (key, value) = pair?;
-                    let key = key.to_str()?;
-                    let value = value.to_str()?;
-                    query.append_pair(&key, &value);
+                let pairs = lua_table_to_query_pairs(lua, &t)?;
+                if !pairs.is_empty() {
+                    url.query_pairs_mut().extend_pairs(pairs);
                 }
             }
 
-            // Extract method
+            // Extract multipart body, if any
+            let multipart = tab.get::<Option<Multipart>>("multipart")?;
+            if multipart.is_some() && !tab.get::<LuaValue>("body")?.is_nil() {
+                return Err(LuaError::RuntimeError(
+                    "Invalid request - 'body' and 'multipart' are mutually exclusive".to_string(),
+                ));
+            }
+
+            // Extract method, defaulting to POST for multipart bodies
             let method = tab.get::<LuaValue>("method")?;
-            let method = lua_value_to_method(&method)?;
+            let method = if method.is_nil() && multipart.is_some() {
+                Method::POST
+            } else {
+                lua_value_to_method(&method)?
+            };
 
             // Extract headers
             let headers = tab.get::<Option<LuaTable>>("headers")?;
-            let headers = headers
+            let mut headers = headers
                 .map(|t| lua_table_to_header_map(&t))
                 .transpose()?
                 .unwrap_or_default();
 
+            // Extract cookies, either a jar or a plain table of name-value pairs
+            let cookies = tab.get::<LuaValue>("cookies")?;
+            let cookies = lua_value_to_cookie_jar(cookies, &mut headers)?;
+
             // Extract body
             let body = tab.get::<ReadableBody>("body")?;
 
@@ -232,6 +293,9 @@ impl FromLua for Request {
                 address: None,
                 redirects: None,
                 decompress: options.decompress,
+                multipart,
+                cookies,
+                proxy: options.proxy,
             })
         } else {
             // Anything else is invalid
@@ -264,5 +328,23 @@ impl LuaUserData for Request {
             header_map_to_table(lua, this.headers().clone(), this.decompress)
         });
         fields.add_field_method_get("body", |lua, this| lua.create_string(this.body()));
+        fields.add_field_method_get("cookies", |lua, this| {
+            let mut builder = TableBuilder::new(lua.clone())?;
+            // Earlier cookies are more specific, and take precedence over later ones
+            for (name, value) in this.cookies().into_iter().rev() {
+                builder = builder.with_value(name, value)?;
+            }
+            builder.build_readonly()
+        });
+        fields.add_field_method_get("form", |lua, this| {
+            this.form()?
+                .map(|form| hash_map_to_table(lua, form.fields, false))
+                .transpose()
+        });
+        fields.add_field_method_get("files", |lua, this| {
+            this.form()?
+                .map(|form| form.files_into_table(lua))
+                .transpose()
+        });
     }
 }
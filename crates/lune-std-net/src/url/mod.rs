@@ -1,5 +1,7 @@
 mod decode;
 mod encode;
+mod parts;
 
 pub use self::decode::decode;
 pub use self::encode::encode;
+pub use self::parts::{UrlParts, parse};
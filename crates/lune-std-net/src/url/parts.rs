@@ -0,0 +1,158 @@
+use mlua::prelude::*;
+use url::Url;
+
+use lune_utils::TableBuilder;
+
+use crate::shared::lua::{lua_table_to_query_pairs, query_pairs_to_lua_table};
+
+/**
+    The individual parts of a URL, which may be converted
+    to and from both Lua tables and fully parsed URLs.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct UrlParts {
+    pub scheme: String,
+    pub username: String,
+    pub password: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub fragment: Option<String>,
+}
+
+impl UrlParts {
+    /**
+        Converts these parts into a full URL.
+
+        Unicode host names are converted to their ASCII form using IDNA,
+        and any characters not allowed in a URL are percent-encoded.
+
+        # Errors
+
+        Errors if the parts do not form a valid URL.
+    */
+    pub fn to_url(&self) -> LuaResult<Url> {
+        let base = match &self.host {
+            Some(host) => format!("{}://{host}", self.scheme),
+            None => format!("{}:", self.scheme),
+        };
+        let mut url = Url::parse(&base).map_err(invalid_url)?;
+
+        if !self.username.is_empty() {
+            url.set_username(&self.username)
+                .map_err(|()| LuaError::runtime("Invalid URL - cannot have a username"))?;
+        }
+        if self.password.is_some() {
+            url.set_password(self.password.as_deref())
+                .map_err(|()| LuaError::runtime("Invalid URL - cannot have a password"))?;
+        }
+        if self.port.is_some() {
+            url.set_port(self.port)
+                .map_err(|()| LuaError::runtime("Invalid URL - cannot have a port"))?;
+        }
+
+        url.set_path(&self.path);
+        if !self.query.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.query);
+        }
+        url.set_fragment(self.fragment.as_deref());
+
+        Ok(url)
+    }
+
+    /**
+        Converts these parts into a Lua table.
+    */
+    pub fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        let host_unicode = self.host.as_deref().map(|host| {
+            let (unicode, result) = idna::domain_to_unicode(host);
+            if result.is_ok() {
+                unicode
+            } else {
+                host.to_string()
+            }
+        });
+
+        TableBuilder::new(lua.clone())?
+            .with_value("scheme", self.scheme)?
+            .with_value("username", self.username)?
+            .with_value("password", self.password)?
+            .with_value("host", self.host)?
+            .with_value("hostUnicode", host_unicode)?
+            .with_value("port", self.port)?
+            .with_value("path", self.path)?
+            .with_value("query", query_pairs_to_lua_table(lua, self.query)?)?
+            .with_value("fragment", self.fragment)?
+            .build()
+    }
+}
+
+impl From<&Url> for UrlParts {
+    fn from(url: &Url) -> Self {
+        Self {
+            scheme: url.scheme().to_string(),
+            username: url.username().to_string(),
+            password: url.password().map(ToString::to_string),
+            host: url.host_str().map(ToString::to_string),
+            port: url.port_or_known_default(),
+            path: url.path().to_string(),
+            query: url.query_pairs().into_owned().collect(),
+            fragment: url.fragment().map(ToString::to_string),
+        }
+    }
+}
+
+impl FromLua for UrlParts {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "UrlParts".to_string(),
+                message: Some(format!(
+                    "Invalid url parts - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let Some(scheme) = tab.get::<Option<String>>("scheme")? else {
+            return Err(LuaError::runtime("Invalid url parts - missing 'scheme'"));
+        };
+
+        let query = match tab.get::<Option<LuaTable>>("query")? {
+            Some(t) => lua_table_to_query_pairs(lua, &t)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            scheme,
+            username: tab.get::<Option<String>>("username")?.unwrap_or_default(),
+            password: tab.get("password")?,
+            host: tab.get("host")?,
+            port: tab.get("port")?,
+            path: tab.get::<Option<String>>("path")?.unwrap_or_default(),
+            query,
+            fragment: tab.get("fragment")?,
+        })
+    }
+}
+
+/**
+    Parses a URL, optionally relative to the given base URL.
+
+    # Errors
+
+    Errors if the URL, or the base URL, is not valid.
+*/
+pub fn parse(url: &str, base: Option<&str>) -> LuaResult<Url> {
+    match base {
+        Some(base) => Url::parse(base).map_err(invalid_url)?.join(url),
+        None => Url::parse(url),
+    }
+    .map_err(invalid_url)
+}
+
+fn invalid_url(e: url::ParseError) -> LuaError {
+    LuaError::runtime(format!("Invalid URL - {e}"))
+}
@@ -1,4 +1,10 @@
-use std::process::ExitStatus;
+use std::{
+    process::ExitStatus,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 use async_channel::{Receiver, Sender, unbounded};
 use async_process::Child as AsyncChild;
@@ -9,15 +15,25 @@ use mlua_luau_scheduler::LuaSpawnExt;
 
 use lune_utils::TableBuilder;
 
-use super::{ChildReader, ChildWriter};
+use crate::signal::{Signal, exit_signal_name};
+
+use crate::options::ProcessSpawnOptionsPty;
+
+#[cfg(unix)]
+use super::pty::{PtyControl, PtyMaster};
+use super::{ChildReader, ChildWriter, read_line_merged};
 
 #[derive(Debug, Clone)]
 pub struct Child {
+    pid: u32,
     stdin: ChildWriter,
     stdout: ChildReader,
     stderr: ChildReader,
     kill_tx: Sender<()>,
     status_rx: Receiver<Option<ExitStatus>>,
+    exited: Arc<AtomicBool>,
+    #[cfg(unix)]
+    pty: Option<PtyControl>,
 }
 
 impl Child {
@@ -25,25 +41,103 @@ impl Child {
         let stdin = ChildWriter::from(child.stdin.take());
         let stdout = ChildReader::from(child.stdout.take());
         let stderr = ChildReader::from(child.stderr.take());
+        Self::from_parts(lua, child, stdin, stdout, stderr)
+    }
+
+    /**
+        Creates a new child process attached to a pseudo-terminal, where
+        stdout and stderr are merged into a single stream, given as stdout.
+    */
+    #[cfg(unix)]
+    pub fn with_pty(lua: &Lua, child: AsyncChild, master: PtyMaster) -> Self {
+        let stdin = ChildWriter::from(master.writer);
+        let stdout = ChildReader::from(master.reader);
+        let stderr = ChildReader::from(None::<async_process::ChildStderr>);
+        let mut this = Self::from_parts(lua, child, stdin, stdout, stderr);
+        this.pty = Some(master.control);
+        this
+    }
+
+    fn from_parts(
+        lua: &Lua,
+        child: AsyncChild,
+        stdin: ChildWriter,
+        stdout: ChildReader,
+        stderr: ChildReader,
+    ) -> Self {
+        let pid = child.id();
 
         // NOTE: Kill channel is zero size, status is very small
         // and implements Copy, unbounded will be just fine here
         let (kill_tx, kill_rx) = unbounded();
         let (status_tx, status_rx) = unbounded();
-        lua.spawn(handle_child(child, kill_rx, status_tx)).detach();
+        let exited = Arc::new(AtomicBool::new(false));
+        lua.spawn(handle_child(child, kill_rx, status_tx, exited.clone()))
+            .detach();
 
         Self {
+            pid,
             stdin,
             stdout,
             stderr,
             kill_tx,
             status_rx,
+            exited,
+            #[cfg(unix)]
+            pty: None,
+        }
+    }
+
+    fn resize(&self, size: ProcessSpawnOptionsPty) -> LuaResult<()> {
+        #[cfg(unix)]
+        if let Some(pty) = &self.pty {
+            return pty.resize(size).into_lua_err();
+        }
+        let _ = size;
+        Err(LuaError::runtime(
+            "Child process was not created with a pseudo-terminal",
+        ))
+    }
+
+    async fn read_line_merged(&self, lua: &Lua) -> LuaResult<LuaMultiValue> {
+        match read_line_merged(&self.stdout, &self.stderr)
+            .await
+            .into_lua_err()?
+        {
+            Some((line, source)) => (lua.create_string(line)?, source.name()).into_lua_multi(lua),
+            None => Ok(LuaMultiValue::new()),
+        }
+    }
+
+    fn signal(&self, signal: Signal) -> LuaResult<()> {
+        // Signals should never be sent after the child has exited, since
+        // its process id may have been reused by some other process
+        if self.exited.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if signal == Signal::Kill {
+            let _ = self.kill_tx.try_send(());
+            return Ok(());
+        }
+        #[cfg(unix)]
+        {
+            signal.send_to_pid(self.pid).into_lua_err()
+        }
+        #[cfg(not(unix))]
+        {
+            if signal == Signal::Term {
+                let _ = self.kill_tx.try_send(());
+                Ok(())
+            } else {
+                Err(crate::signal::unsupported(signal)).into_lua_err()
+            }
         }
     }
 }
 
 impl LuaUserData for Child {
     fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("pid", |_, this| Ok(this.pid));
         fields.add_field_method_get("stdin", |_, this| Ok(this.stdin.clone()));
         fields.add_field_method_get("stdout", |_, this| Ok(this.stdout.clone()));
         fields.add_field_method_get("stderr", |_, this| Ok(this.stderr.clone()));
@@ -54,6 +148,21 @@ impl LuaUserData for Child {
             let _ = this.kill_tx.try_send(());
             Ok(())
         });
+        methods.add_method("signal", |_, this, signal: Signal| this.signal(signal));
+        methods.add_method("resize", |_, this, (rows, cols): (u16, u16)| {
+            this.resize(ProcessSpawnOptionsPty { rows, cols })
+        });
+        methods.add_async_method("readLine", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { this.read_line_merged(&lua).await }
+        });
+        methods.add_method("lines", |lua, this, (): ()| {
+            let this = this.clone();
+            lua.create_async_function(move |lua, (): ()| {
+                let this = this.clone();
+                async move { this.read_line_merged(&lua).await }
+            })
+        });
         methods.add_async_method("status", |lua, this, (): ()| {
             let rx = this.status_rx.clone();
             async move {
@@ -62,6 +171,7 @@ impl LuaUserData for Child {
                 TableBuilder::new(lua.clone())?
                     .with_value("ok", code == 0)?
                     .with_value("code", code)?
+                    .with_value("signal", status.and_then(exit_signal_name))?
                     .build_readonly()
             }
         });
@@ -72,15 +182,18 @@ async fn handle_child(
     mut child: AsyncChild,
     kill_rx: Receiver<()>,
     status_tx: Sender<Option<ExitStatus>>,
+    exited: Arc<AtomicBool>,
 ) {
     let status = select! {
         s = child.status().fuse() => s.ok(), // FUTURE: Propagate this error somehow?
         _ = kill_rx.recv().fuse() => {
             let _ = child.kill(); // Will only error if already killed
-            None
+            child.status().await.ok()
         }
     };
 
+    exited.store(true, Ordering::SeqCst);
+
     // Will only error if there are no receivers waiting for the status
     let _ = status_tx.send(status).await;
 }
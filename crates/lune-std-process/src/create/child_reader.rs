@@ -1,13 +1,36 @@
-use std::sync::Arc;
+use std::{pin::pin, sync::Arc};
 
 use async_lock::Mutex as AsyncMutex;
 use async_process::{ChildStderr as AsyncChildStderr, ChildStdout as AsyncChildStdout};
 use futures_lite::{io, prelude::*};
+use futures_util::future::{Either, select};
 
 use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
+
+#[cfg(unix)]
+use super::pty::PtyReader;
 
 const DEFAULT_BUFFER_SIZE: usize = 1024;
 
+/**
+    The source stream of data read from a child process.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildReaderSource {
+    Stdout,
+    Stderr,
+}
+
+impl ChildReaderSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
 // Inner (plumbing) implementation
 
 #[derive(Debug)]
@@ -15,6 +38,8 @@ enum ChildReaderInner {
     None,
     Stdout(AsyncChildStdout),
     Stderr(AsyncChildStderr),
+    #[cfg(unix)]
+    Pty(PtyReader),
 }
 
 impl ChildReaderInner {
@@ -29,6 +54,8 @@ impl ChildReaderInner {
             ChildReaderInner::None => unreachable!(),
             ChildReaderInner::Stdout(stdout) => stdout.read(&mut buf).await?,
             ChildReaderInner::Stderr(stderr) => stderr.read(&mut buf).await?,
+            #[cfg(unix)]
+            ChildReaderInner::Pty(pty) => pty.read(&mut buf).await?,
         };
 
         buf.truncate(read);
@@ -48,6 +75,10 @@ impl ChildReaderInner {
             ChildReaderInner::Stderr(stderr) => {
                 io::copy(stderr, &mut buf).await?;
             }
+            #[cfg(unix)]
+            ChildReaderInner::Pty(pty) => {
+                io::copy(pty, &mut buf).await?;
+            }
         }
 
         Ok(buf)
@@ -66,6 +97,13 @@ impl From<AsyncChildStderr> for ChildReaderInner {
     }
 }
 
+#[cfg(unix)]
+impl From<PtyReader> for ChildReaderInner {
+    fn from(pty: PtyReader) -> Self {
+        Self::Pty(pty)
+    }
+}
+
 impl From<Option<AsyncChildStdout>> for ChildReaderInner {
     fn from(stdout: Option<AsyncChildStdout>) -> Self {
         stdout.map_or(Self::None, Into::into)
@@ -78,11 +116,97 @@ impl From<Option<AsyncChildStderr>> for ChildReaderInner {
     }
 }
 
+// Buffered implementation, used for delimiter-based reads
+
+#[derive(Debug)]
+struct ChildReaderState {
+    inner: ChildReaderInner,
+    buffer: Vec<u8>,
+}
+
+impl ChildReaderState {
+    async fn read(&mut self, size: usize) -> Result<Vec<u8>, std::io::Error> {
+        if self.buffer.is_empty() {
+            self.inner.read(size).await
+        } else {
+            let len = size.min(self.buffer.len());
+            Ok(self.buffer.drain(..len).collect())
+        }
+    }
+
+    async fn read_to_end(&mut self) -> Result<Vec<u8>, std::io::Error> {
+        let mut buf = std::mem::take(&mut self.buffer);
+        buf.extend(self.inner.read_to_end().await?);
+        Ok(buf)
+    }
+
+    /*
+        NOTE: This is cancellation safe - any data read from the inner reader
+        is stored in the buffer before awaiting again, which makes it fine
+        to use when racing reads from multiple readers against each other
+    */
+    async fn read_until(&mut self, delimiter: &[u8]) -> Result<Option<Vec<u8>>, std::io::Error> {
+        let mut searched = 0;
+        loop {
+            if let Some(pos) = find_delimiter(&self.buffer[searched..], delimiter) {
+                let end = searched + pos + delimiter.len();
+                return Ok(Some(self.buffer.drain(..end).collect()));
+            }
+            searched = self.buffer.len().saturating_sub(delimiter.len() - 1);
+
+            let bytes = self.inner.read(DEFAULT_BUFFER_SIZE).await?;
+            if bytes.is_empty() {
+                return Ok(if self.buffer.is_empty() {
+                    None
+                } else {
+                    Some(std::mem::take(&mut self.buffer))
+                });
+            }
+            self.buffer.extend(bytes);
+        }
+    }
+}
+
+fn find_delimiter(haystack: &[u8], delimiter: &[u8]) -> Option<usize> {
+    haystack
+        .windows(delimiter.len())
+        .position(|window| window == delimiter)
+}
+
+fn strip_line_ending(mut line: Vec<u8>) -> Vec<u8> {
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
 // Outer (lua-accessible, clonable) implementation
 
 #[derive(Debug, Clone)]
 pub struct ChildReader {
-    inner: Arc<AsyncMutex<ChildReaderInner>>,
+    inner: Arc<AsyncMutex<ChildReaderState>>,
+}
+
+impl ChildReader {
+    /**
+        Reads until the given delimiter, returning the data read including the delimiter.
+
+        Returns any remaining data without the delimiter if the reader reached its end,
+        or `None` if there was no data left to read at all.
+    */
+    pub async fn read_until(&self, delimiter: &[u8]) -> Result<Option<Vec<u8>>, std::io::Error> {
+        self.inner.lock().await.read_until(delimiter).await
+    }
+
+    /**
+        Reads a single line, without its trailing line ending.
+    */
+    pub async fn read_line(&self) -> Result<Option<Vec<u8>>, std::io::Error> {
+        Ok(self.read_until(b"\n").await?.map(strip_line_ending))
+    }
 }
 
 impl LuaUserData for ChildReader {
@@ -108,13 +232,98 @@ impl LuaUserData for ChildReader {
                 Ok(lua.create_string(bytes))
             }
         });
+        methods.add_async_method(
+            "readUntil",
+            |lua, this, (delimiter, include): (LuaString, Option<bool>)| {
+                let this = this.clone();
+                let delimiter = delimiter.as_bytes().to_vec();
+                async move {
+                    if delimiter.is_empty() {
+                        return Err(LuaError::runtime("Delimiter must not be empty"));
+                    }
+                    let Some(mut bytes) = this.read_until(&delimiter).await.into_lua_err()? else {
+                        return Ok(LuaValue::Nil);
+                    };
+                    if !include.unwrap_or(false) && bytes.ends_with(&delimiter) {
+                        bytes.truncate(bytes.len() - delimiter.len());
+                    }
+                    Ok(LuaValue::String(lua.create_string(bytes)?))
+                }
+            },
+        );
+        methods.add_async_method("readLine", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { read_line_value(&lua, &this).await }
+        });
+        methods.add_method("lines", |lua, this, (): ()| {
+            let this = this.clone();
+            lua.create_async_function(move |lua, (): ()| {
+                let this = this.clone();
+                async move { read_line_value(&lua, &this).await }
+            })
+        });
+    }
+}
+
+/**
+    Reads a single line from whichever of the two readers has one available first,
+    returning the line along with the source it was read from.
+
+    Returns `None` only once both readers have reached their end.
+*/
+pub async fn read_line_merged(
+    stdout: &ChildReader,
+    stderr: &ChildReader,
+) -> Result<Option<(Vec<u8>, ChildReaderSource)>, std::io::Error> {
+    let out = pin!(stdout.read_line());
+    let err = pin!(stderr.read_line());
+    match select(out, err).await {
+        Either::Left((Ok(None), err)) => {
+            Ok(err.await?.map(|line| (line, ChildReaderSource::Stderr)))
+        }
+        Either::Right((Ok(None), out)) => {
+            Ok(out.await?.map(|line| (line, ChildReaderSource::Stdout)))
+        }
+        Either::Left((line, _)) => Ok(line?.map(|line| (line, ChildReaderSource::Stdout))),
+        Either::Right((line, _)) => Ok(line?.map(|line| (line, ChildReaderSource::Stderr))),
+    }
+}
+
+/**
+    Calls a callback in a new thread, waiting for it to finish.
+
+    Returns `false` if the callback returned `false` to stop, such as to stop reading lines.
+*/
+pub async fn call_callback(
+    lua: &Lua,
+    callback: &LuaFunction,
+    args: impl IntoLuaMulti,
+) -> LuaResult<bool> {
+    let thread_id = lua.push_thread_back(callback.clone(), args)?;
+    lua.track_thread(thread_id);
+    lua.wait_for_thread(thread_id).await;
+
+    let result = lua
+        .get_thread_result(thread_id)
+        .expect("Missing callback thread result")?;
+
+    Ok(!matches!(result.front(), Some(LuaValue::Boolean(false))))
+}
+
+async fn read_line_value(lua: &Lua, reader: &ChildReader) -> LuaResult<LuaValue> {
+    match reader.read_line().await.into_lua_err()? {
+        Some(line) => Ok(LuaValue::String(lua.create_string(line)?)),
+        None => Ok(LuaValue::Nil),
     }
 }
 
 impl<T: Into<ChildReaderInner>> From<T> for ChildReader {
     fn from(inner: T) -> Self {
         Self {
-            inner: Arc::new(AsyncMutex::new(inner.into())),
+            inner: Arc::new(AsyncMutex::new(ChildReaderState {
+                inner: inner.into(),
+                buffer: Vec::new(),
+            })),
         }
     }
 }
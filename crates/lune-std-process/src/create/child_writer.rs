@@ -7,22 +7,41 @@ use futures_lite::prelude::*;
 use bstr::BString;
 use mlua::prelude::*;
 
+#[cfg(unix)]
+use super::pty::PtyWriter;
+
 // Inner (plumbing) implementation
 
 #[derive(Debug)]
-struct ChildWriterInner(Option<AsyncChildStdin>);
+enum ChildWriterInner {
+    None,
+    Stdin(AsyncChildStdin),
+    #[cfg(unix)]
+    Pty(PtyWriter),
+}
 
 impl ChildWriterInner {
     async fn write(&mut self, data: Vec<u8>) -> Result<(), std::io::Error> {
-        if let Some(stdin) = self.0.as_mut() {
-            stdin.write_all(&data).await?;
+        match self {
+            ChildWriterInner::None => {}
+            ChildWriterInner::Stdin(stdin) => stdin.write_all(&data).await?,
+            #[cfg(unix)]
+            ChildWriterInner::Pty(pty) => {
+                pty.write_all(&data).await?;
+                pty.flush().await?;
+            }
         }
         Ok(())
     }
 
     async fn close(&mut self) -> Result<(), std::io::Error> {
-        if let Some(mut stdin) = self.0.take() {
-            stdin.flush().await?;
+        match std::mem::replace(self, ChildWriterInner::None) {
+            ChildWriterInner::None => {}
+            ChildWriterInner::Stdin(mut stdin) => stdin.flush().await?,
+            // NOTE: Closing our end of a pseudo-terminal would hang it up
+            // entirely, so we send the terminal end-of-input character instead
+            #[cfg(unix)]
+            ChildWriterInner::Pty(mut pty) => pty.send_eof().await?,
         }
         Ok(())
     }
@@ -30,13 +49,20 @@ impl ChildWriterInner {
 
 impl From<AsyncChildStdin> for ChildWriterInner {
     fn from(stdin: AsyncChildStdin) -> Self {
-        ChildWriterInner(Some(stdin))
+        ChildWriterInner::Stdin(stdin)
+    }
+}
+
+#[cfg(unix)]
+impl From<PtyWriter> for ChildWriterInner {
+    fn from(pty: PtyWriter) -> Self {
+        ChildWriterInner::Pty(pty)
     }
 }
 
 impl From<Option<AsyncChildStdin>> for ChildWriterInner {
     fn from(stdin: Option<AsyncChildStdin>) -> Self {
-        ChildWriterInner(stdin)
+        stdin.map_or(ChildWriterInner::None, Into::into)
     }
 }
 
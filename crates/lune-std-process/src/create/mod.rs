@@ -1,7 +1,11 @@
 mod child;
 mod child_reader;
 mod child_writer;
+#[cfg(unix)]
+mod pty;
 
 pub use self::child::Child;
-pub use self::child_reader::ChildReader;
+pub use self::child_reader::{ChildReader, call_callback, read_line_merged};
 pub use self::child_writer::ChildWriter;
+#[cfg(unix)]
+pub use self::pty::Pty;
@@ -0,0 +1,206 @@
+use std::{
+    fs::File,
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::process::CommandExt,
+    },
+    pin::Pin,
+    process::{Command, Stdio},
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use blocking::Unblock;
+use futures_lite::prelude::*;
+
+use crate::options::ProcessSpawnOptionsPty;
+
+/**
+    A pseudo-terminal, with both the controlling (master) side
+    and the side that a child process is attached to (slave).
+*/
+#[derive(Debug)]
+pub struct Pty {
+    master: OwnedFd,
+    slave: OwnedFd,
+}
+
+impl Pty {
+    /**
+        Opens a new pseudo-terminal with the given size.
+    */
+    pub fn open(size: ProcessSpawnOptionsPty) -> io::Result<Self> {
+        let mut master = -1;
+        let mut slave = -1;
+        let winsize = size.as_winsize();
+        // SAFETY: openpty writes two valid file descriptors on success, and
+        // the null name & termios pointers are explicitly allowed to be null
+        let res = unsafe {
+            libc::openpty(
+                &raw mut master,
+                &raw mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                (&raw const winsize).cast_mut(),
+            )
+        };
+        if res != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: Both file descriptors were just opened and are owned by us
+        let (master, slave) =
+            unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+        set_cloexec(&master)?;
+        set_cloexec(&slave)?;
+        Ok(Self { master, slave })
+    }
+
+    /**
+        Makes the pseudo-terminal the controlling terminal of
+        a new session for the process spawned by the given command.
+
+        Note that the stdio of the command must also be set, using `stdio`.
+    */
+    pub fn attach(command: &mut Command) {
+        // SAFETY: setsid and ioctl are both async-signal-safe,
+        // and do not allocate or otherwise touch any parent state
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /**
+        Creates a new stdio handle for the child side of this pseudo-terminal.
+    */
+    pub fn stdio(&self) -> io::Result<Stdio> {
+        Ok(Stdio::from(self.slave.try_clone()?))
+    }
+
+    /**
+        Closes the child side of this pseudo-terminal, and returns the controlling side.
+
+        This must be called after spawning the child process, otherwise
+        reading from the controlling side will never reach the end.
+    */
+    pub fn into_master(self) -> io::Result<PtyMaster> {
+        drop(self.slave);
+        let master = File::from(self.master);
+        Ok(PtyMaster {
+            reader: PtyReader(Unblock::new(master.try_clone()?)),
+            writer: PtyWriter(Unblock::new(master.try_clone()?)),
+            control: PtyControl(Arc::new(master)),
+        })
+    }
+}
+
+/**
+    The controlling side of a pseudo-terminal, split into its reader, writer and control handle.
+*/
+#[derive(Debug)]
+pub struct PtyMaster {
+    pub reader: PtyReader,
+    pub writer: PtyWriter,
+    pub control: PtyControl,
+}
+
+/**
+    A handle for controlling a pseudo-terminal, such as resizing it.
+*/
+#[derive(Debug, Clone)]
+pub struct PtyControl(Arc<File>);
+
+impl PtyControl {
+    pub fn resize(&self, size: ProcessSpawnOptionsPty) -> io::Result<()> {
+        let winsize = size.as_winsize();
+        // SAFETY: The file descriptor is valid for as long as we hold the
+        // file, and TIOCSWINSZ only reads from the given winsize struct
+        let res = unsafe {
+            libc::ioctl(
+                self.0.as_raw_fd(),
+                libc::TIOCSWINSZ as _,
+                &raw const winsize,
+            )
+        };
+        if res == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/**
+    Reader for the output of a pseudo-terminal.
+
+    Once the child process exits and the pseudo-terminal is hung up, reading
+    will error on some platforms (EIO on Linux) - this is instead treated as EOF.
+*/
+#[derive(Debug)]
+pub struct PtyReader(Unblock<File>);
+
+impl AsyncRead for PtyReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match Pin::new(&mut self.0).poll_read(cx, buf) {
+            Poll::Ready(Err(e)) if e.raw_os_error() == Some(libc::EIO) => Poll::Ready(Ok(0)),
+            other => other,
+        }
+    }
+}
+
+/**
+    Writer for the input of a pseudo-terminal.
+*/
+#[derive(Debug)]
+pub struct PtyWriter(Unblock<File>);
+
+impl PtyWriter {
+    /**
+        Sends an end-of-transmission character, which signals
+        end of input to a child process reading from the terminal.
+    */
+    pub async fn send_eof(&mut self) -> io::Result<()> {
+        self.0.write_all(&[0x04]).await?;
+        self.0.flush().await
+    }
+}
+
+impl AsyncWrite for PtyWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
+
+fn set_cloexec(fd: &OwnedFd) -> io::Result<()> {
+    // SAFETY: The file descriptor is valid, and F_SETFD only changes its flags
+    let res = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
@@ -1,38 +1,126 @@
+use std::{io::ErrorKind, process::Stdio};
+
 use async_process::Child;
-use futures_lite::prelude::*;
+use futures_lite::{future, prelude::*};
 
 use mlua::prelude::*;
 
-use lune_utils::TableBuilder;
+use lune_utils::{TableBuilder, process::ProcessArgs};
 
-use super::options::ProcessSpawnOptionsStdioKind;
+use crate::{
+    options::{ProcessSpawnOptions, ProcessSpawnOptionsStdioKind},
+    signal::exit_signal_name,
+    which::SpawnContext,
+};
 
 mod tee_writer;
+mod timeout;
 mod wait_for_child;
 
 use self::wait_for_child::wait_for_child;
 
+pub(crate) use self::timeout::ExecTimeout;
+
+pub(crate) use self::wait_for_child::read_with_stdio_kind;
+
+/**
+    Spawns the given program with the given options, and waits for it to exit.
+
+    If `kill_on_drop` is set, the child process is also killed if the returned
+    future is dropped before it exits, such as when it has been cancelled.
+*/
+pub async fn spawn_and_exec(
+    lua: Lua,
+    program: String,
+    args: ProcessArgs,
+    mut options: ProcessSpawnOptions,
+    kill_on_drop: bool,
+) -> LuaResult<LuaTable> {
+    if options.detached.is_some() {
+        return Err(LuaError::runtime(
+            "Invalid options - 'detached' can only be used with process.create",
+        ));
+    }
+    if options.pty.is_some() {
+        return Err(LuaError::runtime(
+            "Invalid options - 'pty' can only be used with process.create",
+        ));
+    }
+
+    let timeout = ExecTimeout::new(options.timeout, options.grace_period);
+
+    // NOTE: Timed out processes are terminated along with their process group,
+    // so unless one was given, we create a new group for the child to lead
+    let isolation = &mut options.isolation;
+    if cfg!(unix)
+        && timeout.is_some()
+        && isolation.process_group.is_none()
+        && !isolation.new_session
+    {
+        isolation.process_group = Some(0);
+    }
+
+    let stdin = options.stdio.stdin.take();
+    let stdout = options.stdio.stdout;
+    let stderr = options.stdio.stderr;
+
+    let stdin_stdio = if stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+
+    let command = options.into_command(program, args);
+    let context = SpawnContext::new(&command);
+    let child = async_process::Command::from(command)
+        .stdin(stdin_stdio)
+        .stdout(stdout.as_stdio())
+        .stderr(stderr.as_stdio())
+        .kill_on_drop(kill_on_drop)
+        .spawn()
+        .map_err(|e| context.error(&e))?;
+
+    exec(lua, child, stdin, stdout, stderr, timeout).await
+}
+
 pub async fn exec(
     lua: Lua,
     mut child: Child,
     stdin: Option<Vec<u8>>,
     stdout: ProcessSpawnOptionsStdioKind,
     stderr: ProcessSpawnOptionsStdioKind,
+    timeout: Option<ExecTimeout>,
 ) -> LuaResult<LuaTable> {
-    // Write to stdin before anything else - if we got it
-    if let Some(stdin) = stdin {
-        let mut child_stdin = child.stdin.take().unwrap();
-        child_stdin.write_all(&stdin).await.into_lua_err()?;
-    }
+    /*
+        NOTE: Stdin is written while waiting for the child, and not before,
+        since a child that never reads all of it would otherwise block us
+        forever, without ever starting the timeout that would terminate it
+
+        A child may also exit, or get terminated, without reading all of its
+        stdin, which is not an error, and we stop writing once it has exited
+    */
+    let child_stdin = stdin.map(|stdin| (child.stdin.take().unwrap(), stdin));
+    let write_stdin = async move {
+        if let Some((mut child_stdin, stdin)) = child_stdin {
+            match child_stdin.write_all(&stdin).await {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into_lua_err()),
+                _ => drop(child_stdin),
+            }
+        }
+        future::pending().await
+    };
 
-    let res = wait_for_child(child, stdout, stderr).await?;
+    let res = write_stdin
+        .or(wait_for_child(child, stdout, stderr, timeout))
+        .await?;
 
     /*
         NOTE: If an exit code was not given by the child process,
         we default to 1 if it yielded any error output, otherwise 0
 
         An exit code may be missing if the process was terminated by
-        some external signal, which is the only time we use this default
+        some external signal, which is the only time we use this default,
+        and the signal itself is also given in the result table if possible
     */
     let code = res
         .status
@@ -43,8 +131,10 @@ pub async fn exec(
     let stdout = lua.create_string(&res.stdout)?;
     let stderr = lua.create_string(&res.stderr)?;
     TableBuilder::new(lua)?
-        .with_value("ok", code == 0)?
+        .with_value("ok", code == 0 && !res.timed_out)?
         .with_value("code", code)?
+        .with_value("signal", exit_signal_name(res.status))?
+        .with_value("timedOut", res.timed_out)?
         .with_value("stdout", stdout)?
         .with_value("stderr", stderr)?
         .build_readonly()
@@ -0,0 +1,66 @@
+use std::{io, process::ExitStatus, time::Duration};
+
+use async_io::Timer;
+use async_process::Child;
+use futures_lite::future;
+
+use crate::signal::Signal;
+
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/**
+    A timeout for a child process, after which it is first asked to
+    terminate, and then forcefully killed if the grace period runs out.
+
+    On unix, the child process should be spawned in its own process group,
+    so that any processes it spawns in turn are terminated along with it.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ExecTimeout {
+    pub duration: Duration,
+    pub grace_period: Duration,
+}
+
+impl ExecTimeout {
+    pub fn new(duration: Option<Duration>, grace_period: Option<Duration>) -> Option<Self> {
+        duration.map(|duration| Self {
+            duration,
+            grace_period: grace_period.unwrap_or(DEFAULT_GRACE_PERIOD),
+        })
+    }
+
+    /**
+        Waits for the child process to exit, terminating it if the timeout runs out.
+
+        Returns the exit status of the child, and if it timed out.
+    */
+    pub async fn wait(self, child: &mut Child) -> io::Result<(ExitStatus, bool)> {
+        if let Some(status) = wait_or_timeout(child, self.duration).await {
+            return Ok((status?, false));
+        }
+
+        // NOTE: Sending signals may fail if the child exited right
+        // as we timed out, in which case we get its status below
+        let _ = Signal::Term.send_to_group(child);
+        let status = if let Some(status) = wait_or_timeout(child, self.grace_period).await {
+            status?
+        } else {
+            let _ = Signal::Kill.send_to_group(child);
+            child.status().await?
+        };
+
+        // NOTE: Any processes that the child spawned may still be running and
+        // holding on to its output pipes, which we would then wait for forever,
+        // so we also kill whatever is left in its process group once it exited
+        let _ = Signal::Kill.send_to_group(child);
+        Ok((status, true))
+    }
+}
+
+async fn wait_or_timeout(child: &mut Child, duration: Duration) -> Option<io::Result<ExitStatus>> {
+    future::or(async { Some(child.status().await) }, async {
+        Timer::after(duration).await;
+        None
+    })
+    .await
+}
@@ -7,17 +7,18 @@ use blocking::Unblock;
 use futures_lite::{io, prelude::*};
 use futures_util::try_join;
 
-use super::tee_writer::AsyncTeeWriter;
+use super::{tee_writer::AsyncTeeWriter, timeout::ExecTimeout};
 use crate::options::ProcessSpawnOptionsStdioKind;
 
 #[derive(Debug, Clone)]
 pub(super) struct WaitForChildResult {
     pub status: ExitStatus,
+    pub timed_out: bool,
     pub stdout: Vec<u8>,
     pub stderr: Vec<u8>,
 }
 
-async fn read_with_stdio_kind<R>(
+pub(crate) async fn read_with_stdio_kind<R>(
     read_from: Option<R>,
     kind: ProcessSpawnOptionsStdioKind,
 ) -> LuaResult<Vec<u8>>
@@ -54,18 +55,25 @@ pub(super) async fn wait_for_child(
     mut child: Child,
     stdout_kind: ProcessSpawnOptionsStdioKind,
     stderr_kind: ProcessSpawnOptionsStdioKind,
+    timeout: Option<ExecTimeout>,
 ) -> LuaResult<WaitForChildResult> {
     let stdout_opt = child.stdout.take();
     let stderr_opt = child.stderr.take();
 
-    let (status, stdout, stderr) = try_join!(
-        async { child.status().await.into_lua_err() },
+    let ((status, timed_out), stdout, stderr) = try_join!(
+        async {
+            match timeout {
+                Some(timeout) => timeout.wait(&mut child).await.into_lua_err(),
+                None => Ok((child.status().await.into_lua_err()?, false)),
+            }
+        },
         read_with_stdio_kind(stdout_opt, stdout_kind),
         read_with_stdio_kind(stderr_opt, stderr_kind)
     )?;
 
     Ok(WaitForChildResult {
         status,
+        timed_out,
         stdout,
         stderr,
     })
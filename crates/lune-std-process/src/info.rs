@@ -0,0 +1,164 @@
+use std::{ffi::OsStr, path::Path, thread::available_parallelism};
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users};
+
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+/**
+    Returns the id of the parent process of the current process, if any.
+*/
+pub fn parent_id() -> Option<u32> {
+    #[cfg(unix)]
+    {
+        Some(std::os::unix::process::parent_id())
+    }
+    #[cfg(not(unix))]
+    {
+        let pid = Pid::from_u32(std::process::id());
+        let mut system = System::new();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::nothing(),
+        );
+        system
+            .process(pid)
+            .and_then(|process| process.parent())
+            .map(Pid::as_u32)
+    }
+}
+
+/**
+    Returns the path to the executable of the current process, if it can be found.
+*/
+pub fn exec_path() -> Option<String> {
+    std::env::current_exe()
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+pub fn hostname(_: &Lua, (): ()) -> LuaResult<Option<String>> {
+    Ok(System::host_name())
+}
+
+pub fn cpu_count(_: &Lua, (): ()) -> LuaResult<usize> {
+    // NOTE: This takes cpu affinity and quotas into account where
+    // possible, which is what we want when sizing parallel work
+    Ok(available_parallelism().map_or(1, Into::into))
+}
+
+pub fn uptime(_: &Lua, (): ()) -> LuaResult<u64> {
+    Ok(System::uptime())
+}
+
+pub fn memory(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    let mut system = System::new();
+    system.refresh_memory();
+    TableBuilder::new(lua.clone())?
+        .with_value("total", system.total_memory())?
+        .with_value("free", system.free_memory())?
+        .with_value("available", system.available_memory())?
+        .build_readonly()
+}
+
+pub fn user(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    let pid = Pid::from_u32(std::process::id());
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        false,
+        ProcessRefreshKind::nothing().with_user(UpdateKind::Always),
+    );
+
+    let users = Users::new_with_refreshed_list();
+    let user = system
+        .process(pid)
+        .and_then(|process| process.user_id())
+        .and_then(|uid| users.get_user_by_id(uid));
+
+    let name = user.map(|user| user.name().to_string()).or_else(|| {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok()
+    });
+    let home = directories::UserDirs::new().map(|dirs| path_to_string(dirs.home_dir()));
+
+    #[cfg(unix)]
+    let (uid, gid) = {
+        // SAFETY: getuid and getgid are always successful, and have no memory safety requirements
+        unsafe { (Some(libc::getuid()), Some(libc::getgid())) }
+    };
+    #[cfg(not(unix))]
+    let (uid, gid) = (None::<u32>, None::<u32>);
+
+    TableBuilder::new(lua.clone())?
+        .with_value("name", name)?
+        .with_value("home", home)?
+        .with_value("uid", uid)?
+        .with_value("gid", gid)?
+        .build_readonly()
+}
+
+pub async fn list(lua: Lua, (): ()) -> LuaResult<LuaTable> {
+    let processes = blocking::unblock(list_processes).await;
+
+    let table = lua.create_table_with_capacity(processes.len(), 0)?;
+    for process in processes {
+        let info = TableBuilder::new(lua.clone())?
+            .with_value("pid", process.pid)?
+            .with_value("ppid", process.ppid)?
+            .with_value("name", process.name)?
+            .with_value("cmd", process.cmd)?
+            .with_value("exe", process.exe)?
+            .build_readonly()?;
+        table.push(info)?;
+    }
+
+    Ok(table)
+}
+
+struct ProcessInfo {
+    pid: u32,
+    ppid: Option<u32>,
+    name: String,
+    cmd: Vec<String>,
+    exe: Option<String>,
+}
+
+fn list_processes() -> Vec<ProcessInfo> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_cmd(UpdateKind::Always)
+            .with_exe(UpdateKind::Always),
+    );
+
+    let mut processes = system
+        .processes()
+        .values()
+        // NOTE: Threads are listed as processes on some platforms, skip them
+        .filter(|process| process.thread_kind().is_none())
+        .map(|process| ProcessInfo {
+            pid: process.pid().as_u32(),
+            ppid: process.parent().map(Pid::as_u32),
+            name: os_str_to_string(process.name()),
+            cmd: process.cmd().iter().map(|s| os_str_to_string(s)).collect(),
+            exe: process.exe().map(path_to_string),
+        })
+        .collect::<Vec<_>>();
+
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+fn os_str_to_string(s: &OsStr) -> String {
+    s.to_string_lossy().into_owned()
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
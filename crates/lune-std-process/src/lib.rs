@@ -1,7 +1,10 @@
 #![allow(clippy::cargo_common_metadata)]
 
 use std::{
-    env::consts::{ARCH, OS},
+    env::{
+        consts::{ARCH, OS},
+        current_dir, set_current_dir,
+    },
     path::MAIN_SEPARATOR,
     process::Stdio,
 };
@@ -17,9 +20,19 @@ use lune_utils::{
 
 mod create;
 mod exec;
+mod info;
 mod options;
+mod pipeline;
+mod pool;
+mod signal;
+mod which;
 
-use self::options::ProcessSpawnOptions;
+use self::{
+    options::{ProcessSpawnOptions, ProcessSpawnOptionsDetached, ProcessSpawnOptionsPty},
+    pipeline::{PipelineOptions, PipelineStage},
+    pool::{PoolCommand, PoolOptions},
+    signal::{Signal, SignalHandlers},
+};
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
@@ -31,6 +44,27 @@ pub fn typedefs() -> String {
     TYPEDEFS.to_string()
 }
 
+/**
+    Starts listening for signals that would normally terminate the process, such as
+    `SIGINT` and `SIGTERM`, so that the scheduler exits gracefully when one is received
+    without any handlers connected using `process.onSignal`, instead of the process
+    being terminated immediately.
+
+    If another signal is received before the scheduler was able to handle the first,
+    such as when a Lua thread never yields, the process is terminated immediately.
+
+    # Errors
+
+    Errors if listening for any of the signals fails.
+
+    # Panics
+
+    Panics if called outside of a running scheduler.
+*/
+pub fn listen_for_signals(lua: &Lua) -> LuaResult<()> {
+    SignalHandlers::listen_for_defaults(lua)
+}
+
 /**
     Creates the `process` standard library module.
 
@@ -75,7 +109,7 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     let process_exit = fns.exit;
 
     // Create the full process table
-    TableBuilder::new(lua)?
+    let process = TableBuilder::new(lua.clone())?
         .with_value("os", os)?
         .with_value("arch", arch)?
         .with_value("endianness", endianness)?
@@ -83,45 +117,196 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_value("cwd", cwd_str)?
         .with_value("env", process_env)?
         .with_value("exit", process_exit)?
+        .with_value("pid", std::process::id())?
+        .with_value("ppid", info::parent_id())?
+        .with_value("execPath", info::exec_path())?
+        .with_function("hostname", info::hostname)?
+        .with_function("cpuCount", info::cpu_count)?
+        .with_function("memory", info::memory)?
+        .with_function("uptime", info::uptime)?
+        .with_function("user", info::user)?
+        .with_async_function("list", info::list)?
         .with_async_function("exec", process_exec)?
         .with_function("create", process_create)?
-        .build_readonly()
+        .with_async_function("pipeline", process_pipeline)?
+        .with_async_function("pool", process_pool)?
+        .with_function("onSignal", process_on_signal)?
+        .with_function("which", process_which)?
+        .build()?;
+
+    // Changing the working directory also needs to update the cwd in the process table
+    let process_inner = process.clone();
+    let process_chdir = lua.create_function(move |_, path: String| {
+        let cwd = change_current_dir(&path)?;
+        process_inner.set_readonly(false);
+        let result = process_inner.raw_set("cwd", cwd);
+        process_inner.set_readonly(true);
+        result
+    })?;
+    process.raw_set("chdir", process_chdir)?;
+
+    process.set_readonly(true);
+    Ok(process)
 }
 
 async fn process_exec(
     lua: Lua,
-    (program, args, mut options): (String, ProcessArgs, ProcessSpawnOptions),
+    (program, args, options): (String, ProcessArgs, ProcessSpawnOptions),
 ) -> LuaResult<LuaTable> {
-    let stdin = options.stdio.stdin.take();
-    let stdout = options.stdio.stdout;
-    let stderr = options.stdio.stderr;
-
-    let stdin_stdio = if stdin.is_some() {
-        Stdio::piped()
-    } else {
-        Stdio::null()
-    };
-
-    let child = options
-        .into_command(program, args)
-        .stdin(stdin_stdio)
-        .stdout(stdout.as_stdio())
-        .stderr(stderr.as_stdio())
-        .spawn()?;
-
-    exec::exec(lua, child, stdin, stdout, stderr).await
+    exec::spawn_and_exec(lua, program, args, options, false).await
 }
 
 fn process_create(
     lua: &Lua,
-    (program, args, options): (String, ProcessArgs, ProcessSpawnOptions),
+    (program, args, mut options): (String, ProcessArgs, ProcessSpawnOptions),
 ) -> LuaResult<LuaValue> {
-    let child = options
-        .into_command(program, args)
+    if let Some(detached) = options.detached.take() {
+        return process_create_detached(lua, program, args, options, &detached);
+    }
+    if let Some(size) = options.pty {
+        return process_create_pty(lua, program, args, options, size);
+    }
+
+    let command = options.into_command(program, args);
+    let context = which::SpawnContext::new(&command);
+    let child = async_process::Command::from(command)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .spawn()?;
+        .spawn()
+        .map_err(|e| context.error(&e))?;
 
     create::Child::new(lua, child).into_lua(lua)
 }
+
+fn process_create_detached(
+    lua: &Lua,
+    program: String,
+    args: ProcessArgs,
+    options: ProcessSpawnOptions,
+    detached: &ProcessSpawnOptionsDetached,
+) -> LuaResult<LuaValue> {
+    let (stdin, stdout, stderr) = detached.open_stdio().map_err(|e| {
+        LuaError::runtime(format!(
+            "Failed to open stdio files for detached process - {e}"
+        ))
+    })?;
+
+    #[cfg_attr(not(windows), allow(unused_mut))]
+    let mut command = options.into_command(program, args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+
+    let context = which::SpawnContext::new(&command);
+    let mut child = async_process::Command::from(command)
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(stderr)
+        .spawn()
+        .map_err(|e| context.error(&e))?;
+
+    let pid = child.id();
+    if let Err(e) = detached.write_pidfile(pid) {
+        let _ = child.kill();
+        return Err(LuaError::runtime(format!(
+            "Failed to write pidfile for detached process - {e}"
+        )));
+    }
+
+    // NOTE: Dropping the child does not kill it, it keeps running in the background and
+    // gets reaped once it exits, without keeping the scheduler or runtime alive meanwhile
+    drop(child);
+
+    pid.into_lua(lua)
+}
+
+#[cfg(unix)]
+fn process_create_pty(
+    lua: &Lua,
+    program: String,
+    args: ProcessArgs,
+    options: ProcessSpawnOptions,
+    size: ProcessSpawnOptionsPty,
+) -> LuaResult<LuaValue> {
+    let pty = create::Pty::open(size)?;
+
+    let mut command = options.into_command(program, args);
+    create::Pty::attach(&mut command);
+
+    let context = which::SpawnContext::new(&command);
+    let child = async_process::Command::from(command)
+        .stdin(pty.stdio()?)
+        .stdout(pty.stdio()?)
+        .stderr(pty.stdio()?)
+        .spawn()
+        .map_err(|e| context.error(&e))?;
+
+    create::Child::with_pty(lua, child, pty.into_master()?).into_lua(lua)
+}
+
+#[cfg(not(unix))]
+fn process_create_pty(
+    _: &Lua,
+    _: String,
+    _: ProcessArgs,
+    _: ProcessSpawnOptions,
+    _: ProcessSpawnOptionsPty,
+) -> LuaResult<LuaValue> {
+    Err(LuaError::runtime(
+        "Pseudo-terminals are not supported on this platform",
+    ))
+}
+
+async fn process_pipeline(
+    lua: Lua,
+    (stages, options): (Vec<PipelineStage>, PipelineOptions),
+) -> LuaResult<LuaTable> {
+    pipeline::pipeline(lua, stages, options).await
+}
+
+async fn process_pool(
+    lua: Lua,
+    (commands, options): (Vec<PoolCommand>, PoolOptions),
+) -> LuaResult<LuaTable> {
+    pool::pool(lua, commands, options).await
+}
+
+fn process_which(_: &Lua, name: String) -> LuaResult<Option<String>> {
+    let path_env = std::env::var_os("PATH");
+    let cwd = current_dir().into_lua_err()?;
+    Ok(which::which(name.as_ref(), path_env.as_deref(), &cwd)
+        .map(|path| path.to_string_lossy().into_owned()))
+}
+
+fn process_on_signal(
+    lua: &Lua,
+    (signal, callback): (Signal, LuaFunction),
+) -> LuaResult<LuaFunction> {
+    SignalHandlers::connect(lua, signal, callback)
+}
+
+fn change_current_dir(path: &str) -> LuaResult<String> {
+    set_current_dir(path).map_err(|e| {
+        LuaError::runtime(format!(
+            "Failed to change working directory to '{path}' - {e}"
+        ))
+    })?;
+
+    // NOTE: We use the same format as the initial process.cwd
+    // here, an absolute path ending with a path separator
+    let mut cwd = current_dir()
+        .into_lua_err()?
+        .to_str()
+        .ok_or_else(|| LuaError::runtime("Working directory is not valid UTF-8"))?
+        .to_string();
+    if !cwd.ends_with(MAIN_SEPARATOR) {
+        cwd.push(MAIN_SEPARATOR);
+    }
+
+    Ok(cwd)
+}
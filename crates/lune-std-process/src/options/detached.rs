@@ -0,0 +1,133 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use mlua::prelude::*;
+
+/**
+    A file to redirect an output stream of a detached process to.
+*/
+#[derive(Debug, Clone)]
+pub struct ProcessSpawnOptionsDetachedOutput {
+    pub path: PathBuf,
+    pub append: bool,
+}
+
+impl ProcessSpawnOptionsDetachedOutput {
+    fn open(&self) -> io::Result<File> {
+        File::options()
+            .create(true)
+            .write(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(&self.path)
+    }
+}
+
+impl FromLua for ProcessSpawnOptionsDetachedOutput {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Table(t) => {
+                let Some(path) = t.get::<Option<String>>("file")? else {
+                    return Err(LuaError::runtime(
+                        "Invalid detached output - missing 'file'",
+                    ));
+                };
+                let append = t.get::<Option<bool>>("append")?.unwrap_or_default();
+                Ok(Self {
+                    path: PathBuf::from(path),
+                    append,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ProcessSpawnOptionsDetachedOutput".to_string(),
+                message: Some(format!(
+                    "Invalid detached output - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Options for a detached process, which runs in the background and
+    may outlive the current process, with its stdio redirected to files.
+
+    Any streams without a file are redirected to the null device.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSpawnOptionsDetached {
+    pub stdin: Option<PathBuf>,
+    pub stdout: Option<ProcessSpawnOptionsDetachedOutput>,
+    pub stderr: Option<ProcessSpawnOptionsDetachedOutput>,
+    pub pidfile: Option<PathBuf>,
+}
+
+impl ProcessSpawnOptionsDetached {
+    /**
+        Opens all of the files to redirect stdio to, returning
+        the stdio to use for stdin, stdout and stderr, in order.
+    */
+    pub fn open_stdio(&self) -> io::Result<(Stdio, Stdio, Stdio)> {
+        let stdin = match &self.stdin {
+            Some(path) => Stdio::from(File::open(path)?),
+            None => Stdio::null(),
+        };
+        let stdout = match &self.stdout {
+            Some(output) => Stdio::from(output.open()?),
+            None => Stdio::null(),
+        };
+        let stderr = match &self.stderr {
+            Some(output) => Stdio::from(output.open()?),
+            None => Stdio::null(),
+        };
+        Ok((stdin, stdout, stderr))
+    }
+
+    /**
+        Writes the given process id to the pidfile, if any.
+    */
+    pub fn write_pidfile(&self, pid: u32) -> io::Result<()> {
+        match &self.pidfile {
+            Some(path) => write_pidfile(path, pid),
+            None => Ok(()),
+        }
+    }
+}
+
+fn write_pidfile(path: &Path, pid: u32) -> io::Result<()> {
+    std::fs::write(path, format!("{pid}\n"))
+}
+
+impl FromLua for ProcessSpawnOptionsDetached {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Boolean(true) => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                stdin: t.get::<Option<String>>("stdin")?.map(PathBuf::from),
+                stdout: match t.get("stdout")? {
+                    LuaValue::Nil => None,
+                    value => Some(ProcessSpawnOptionsDetachedOutput::from_lua(value, lua)?),
+                },
+                stderr: match t.get("stderr")? {
+                    LuaValue::Nil => None,
+                    value => Some(ProcessSpawnOptionsDetachedOutput::from_lua(value, lua)?),
+                },
+                pidfile: t.get::<Option<String>>("pidfile")?.map(PathBuf::from),
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ProcessSpawnOptionsDetached".to_string(),
+                message: Some(format!(
+                    "Invalid detached options - expected boolean or table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
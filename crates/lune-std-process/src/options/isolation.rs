@@ -0,0 +1,198 @@
+use mlua::prelude::*;
+
+// NOTE: The type for resources in setrlimit differs between platforms
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+/**
+    Resource limits for a child process, applied using `setrlimit` on unix.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessSpawnOptionsLimits {
+    pub cpu: Option<u64>,
+    pub memory: Option<u64>,
+    pub files: Option<u64>,
+    pub file_size: Option<u64>,
+}
+
+impl ProcessSpawnOptionsLimits {
+    fn is_empty(&self) -> bool {
+        self.cpu.is_none()
+            && self.memory.is_none()
+            && self.files.is_none()
+            && self.file_size.is_none()
+    }
+
+    #[cfg(unix)]
+    fn as_raw(self) -> Vec<(RlimitResource, libc::rlim_t)> {
+        [
+            (libc::RLIMIT_CPU, self.cpu),
+            (libc::RLIMIT_AS, self.memory),
+            (libc::RLIMIT_NOFILE, self.files),
+            (libc::RLIMIT_FSIZE, self.file_size),
+        ]
+        .into_iter()
+        .filter_map(|(resource, limit)| limit.map(|l| (resource, l as libc::rlim_t)))
+        .collect()
+    }
+}
+
+impl FromLua for ProcessSpawnOptionsLimits {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                cpu: t.get("cpu")?,
+                memory: t.get("memory")?,
+                files: t.get("files")?,
+                file_size: t.get("fileSize")?,
+            }),
+            _ => Err(LuaError::RuntimeError(format!(
+                "Invalid type for option 'limits' - expected table, got '{}'",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/**
+    Options for isolating a child process from the current process,
+    such as its environment, user, process group, and resource limits.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSpawnOptionsIsolation {
+    pub clear_env: bool,
+    pub remove_env: Vec<String>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub process_group: Option<i32>,
+    pub new_session: bool,
+    pub limits: ProcessSpawnOptionsLimits,
+}
+
+impl ProcessSpawnOptionsIsolation {
+    /**
+        Returns `true` if any options that require a unix platform are set.
+    */
+    pub fn requires_unix(&self) -> bool {
+        self.uid.is_some()
+            || self.gid.is_some()
+            || self.process_group.is_some()
+            || self.new_session
+            || !self.limits.is_empty()
+    }
+
+    /**
+        Applies the environment options to the given command.
+
+        This must be called before adding any other environment variables.
+    */
+    pub fn apply_env(&self, cmd: &mut std::process::Command) {
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        for key in &self.remove_env {
+            cmd.env_remove(key);
+        }
+    }
+
+    /**
+        Applies the user, process group, session, and resource limit options to the given command.
+    */
+    #[cfg(unix)]
+    pub fn apply_unix(&self, cmd: &mut std::process::Command) {
+        use std::{io, os::unix::process::CommandExt};
+
+        if let Some(uid) = self.uid {
+            cmd.uid(uid);
+        }
+        if let Some(gid) = self.gid {
+            cmd.gid(gid);
+        }
+        if let Some(pgroup) = self.process_group {
+            cmd.process_group(pgroup);
+        }
+
+        let new_session = self.new_session;
+        let limits = self.limits.as_raw();
+        if !new_session && limits.is_empty() {
+            return;
+        }
+
+        // SAFETY: setsid and setrlimit are both async-signal-safe, and
+        // the limits were collected beforehand, so nothing is allocated
+        unsafe {
+            cmd.pre_exec(move || {
+                if new_session && libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                for &(resource, limit) in &limits {
+                    let rlimit = libc::rlimit {
+                        rlim_cur: limit,
+                        rlim_max: limit,
+                    };
+                    if libc::setrlimit(resource, &raw const rlimit) == -1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+impl FromLua for ProcessSpawnOptionsIsolation {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(value) = value else {
+            return Ok(Self::default());
+        };
+
+        let mut this = Self {
+            clear_env: value.get::<Option<bool>>("clearEnv")?.unwrap_or_default(),
+            uid: value.get("uid")?,
+            gid: value.get("gid")?,
+            new_session: value.get::<Option<bool>>("newSession")?.unwrap_or_default(),
+            limits: ProcessSpawnOptionsLimits::from_lua(value.get("limits")?, lua)?,
+            ..Default::default()
+        };
+
+        if let Some(remove_env) = value.get::<Option<LuaTable>>("removeEnv")? {
+            for key in remove_env.sequence_values::<String>() {
+                let key = key.context("Environment variables to remove must be strings")?;
+                this.remove_env.push(key);
+            }
+        }
+
+        /*
+            A process group may be given as a specific process group id to join,
+            or as `true` to create a new process group for the child process
+        */
+        this.process_group = match value.get("processGroup")? {
+            LuaValue::Nil | LuaValue::Boolean(false) => None,
+            LuaValue::Boolean(true) => Some(0),
+            LuaValue::Integer(i) => Some(i32::try_from(i).into_lua_err()?),
+            value => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid type for option 'processGroup' - expected number or boolean, got '{}'",
+                    value.type_name()
+                )));
+            }
+        };
+
+        if this.new_session && this.process_group.is_some() {
+            return Err(LuaError::runtime(
+                "Invalid options - 'newSession' and 'processGroup' can not be used together",
+            ));
+        }
+        if !cfg!(unix) && this.requires_unix() {
+            return Err(LuaError::runtime(
+                "Invalid options - 'uid', 'gid', 'processGroup', 'newSession' \
+                and 'limits' are only supported on unix platforms",
+            ));
+        }
+
+        Ok(this)
+    }
+}
@@ -3,18 +3,25 @@ use std::{
     env::{self},
     ffi::OsString,
     path::PathBuf,
+    process::Command,
+    time::Duration,
 };
 
 use lune_utils::process::ProcessArgs;
 use mlua::prelude::*;
 
-use async_process::Command;
 use directories::UserDirs;
 
+mod detached;
+mod isolation;
 mod kind;
+mod pty;
 mod stdio;
 
+pub(super) use detached::*;
+pub(super) use isolation::*;
 pub(super) use kind::*;
+pub(super) use pty::*;
 pub(super) use stdio::*;
 
 #[derive(Debug, Clone, Default)]
@@ -23,10 +30,15 @@ pub(super) struct ProcessSpawnOptions {
     pub envs: HashMap<String, String>,
     pub shell: Option<String>,
     pub stdio: ProcessSpawnOptionsStdio,
+    pub timeout: Option<Duration>,
+    pub grace_period: Option<Duration>,
+    pub pty: Option<ProcessSpawnOptionsPty>,
+    pub isolation: ProcessSpawnOptionsIsolation,
+    pub detached: Option<ProcessSpawnOptionsDetached>,
 }
 
 impl FromLua for ProcessSpawnOptions {
-    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
         let mut this = Self::default();
         let value = match value {
             LuaValue::Nil => return Ok(this),
@@ -119,6 +131,43 @@ impl FromLua for ProcessSpawnOptions {
             }
         }
 
+        /*
+            If we got a timeout and / or grace period, make sure they are valid durations
+        */
+        this.timeout = duration_option(&value, "timeout")?;
+        this.grace_period = duration_option(&value, "gracePeriod")?;
+
+        /*
+            If we got a pseudo-terminal to use, parse its size, if any
+        */
+        match value.get("pty")? {
+            LuaValue::Nil | LuaValue::Boolean(false) => {}
+            value => this.pty = Some(ProcessSpawnOptionsPty::from_lua(value, lua)?),
+        }
+
+        /*
+            If we got any options for isolating the process, parse those too,
+            making sure that they are compatible with the pseudo-terminal
+        */
+        this.isolation =
+            ProcessSpawnOptionsIsolation::from_lua(LuaValue::Table(value.clone()), lua)?;
+        if this.pty.is_some()
+            && (this.isolation.new_session || this.isolation.process_group.is_some())
+        {
+            return Err(LuaError::runtime(
+                "Invalid options - 'pty' always creates a new session, and can not be used together with 'newSession' or 'processGroup'",
+            ));
+        }
+
+        /*
+            If we got options for detaching the process, parse those too - detached
+            processes run in their own session on unix, same as with 'newSession'
+        */
+        match value.get("detached")? {
+            LuaValue::Nil | LuaValue::Boolean(false) => {}
+            value => this.detach(ProcessSpawnOptionsDetached::from_lua(value, lua)?)?,
+        }
+
         /*
             If we got options for stdio handling, parse those as well
 
@@ -131,6 +180,19 @@ impl FromLua for ProcessSpawnOptions {
 }
 
 impl ProcessSpawnOptions {
+    fn detach(&mut self, detached: ProcessSpawnOptionsDetached) -> LuaResult<()> {
+        if self.pty.is_some() || self.isolation.process_group.is_some() {
+            return Err(LuaError::runtime(
+                "Invalid options - 'detached' can not be used together with 'pty' or 'processGroup'",
+            ));
+        }
+        self.detached = Some(detached);
+        if cfg!(unix) {
+            self.isolation.new_session = true;
+        }
+        Ok(())
+    }
+
     pub fn into_command(self, program: impl Into<OsString>, args: ProcessArgs) -> Command {
         let mut program: OsString = program.into();
         let mut args = args.into_iter().collect::<Vec<_>>();
@@ -154,10 +216,35 @@ impl ProcessSpawnOptions {
         if let Some(cwd) = self.cwd {
             cmd.current_dir(cwd);
         }
+        self.isolation.apply_env(&mut cmd);
         if !self.envs.is_empty() {
             cmd.envs(self.envs);
         }
 
+        // Set user, process group, and resource limits
+        #[cfg(unix)]
+        self.isolation.apply_unix(&mut cmd);
+
         cmd
     }
 }
+
+fn duration_option(table: &LuaTable, key: &str) -> LuaResult<Option<Duration>> {
+    match table.get(key)? {
+        LuaValue::Nil => Ok(None),
+        LuaValue::Integer(i) => duration_option_from_secs(key, i as f64),
+        LuaValue::Number(n) => duration_option_from_secs(key, n),
+        value => Err(LuaError::RuntimeError(format!(
+            "Invalid type for option '{key}' - expected number, got '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+fn duration_option_from_secs(key: &str, secs: f64) -> LuaResult<Option<Duration>> {
+    Duration::try_from_secs_f64(secs).map(Some).map_err(|_| {
+        LuaError::RuntimeError(format!(
+            "Invalid value for option '{key}' - expected a positive number of seconds, got {secs}"
+        ))
+    })
+}
@@ -0,0 +1,54 @@
+use mlua::prelude::*;
+
+/**
+    Size of a pseudo-terminal for a child process, in rows and columns.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessSpawnOptionsPty {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl ProcessSpawnOptionsPty {
+    #[cfg(unix)]
+    pub fn as_winsize(self) -> libc::winsize {
+        libc::winsize {
+            ws_row: self.rows,
+            ws_col: self.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+impl Default for ProcessSpawnOptionsPty {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+impl FromLua for ProcessSpawnOptionsPty {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil | LuaValue::Boolean(true) => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let mut this = Self::default();
+                if let Some(rows) = t.get::<Option<u16>>("rows")? {
+                    this.rows = rows;
+                }
+                if let Some(cols) = t.get::<Option<u16>>("cols")? {
+                    this.cols = cols;
+                }
+                Ok(this)
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ProcessSpawnOptionsPty".to_string(),
+                message: Some(format!(
+                    "Invalid pty options - expected boolean or table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
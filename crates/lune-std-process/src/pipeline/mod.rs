@@ -0,0 +1,190 @@
+use std::process::Stdio;
+
+use async_process::{Child, Command};
+use futures_lite::prelude::*;
+use futures_util::{future::try_join_all, try_join};
+
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+use crate::{
+    exec::read_with_stdio_kind, options::ProcessSpawnOptionsStdioKind, signal::exit_signal_name,
+    which::SpawnContext,
+};
+
+mod options;
+mod stage;
+
+pub(crate) use self::options::PipelineOptions;
+pub(crate) use self::stage::PipelineStage;
+
+struct StageResult {
+    code: i32,
+    signal: Option<String>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+/**
+    Runs the given stages as a pipeline, connecting the stdout of each stage
+    directly to the stdin of the next one, and waits for all of them to exit.
+*/
+pub async fn pipeline(
+    lua: Lua,
+    stages: Vec<PipelineStage>,
+    options: PipelineOptions,
+) -> LuaResult<LuaTable> {
+    if stages.is_empty() {
+        return Err(LuaError::runtime("Pipeline must have at least one stage"));
+    }
+
+    let mut children = spawn_stages(stages, &options).await?;
+
+    // Write to stdin of the first stage while we also wait for the pipeline,
+    // since the last stage may otherwise fill up its stdout and block forever
+    let stdin = options.stdin;
+    let stdin_child = children[0].stdin.take();
+    let write_stdin = async move {
+        if let (Some(stdin), Some(mut child_stdin)) = (stdin, stdin_child) {
+            // The first stage may exit without reading all of its stdin, which is fine
+            let _ = child_stdin.write_all(&stdin).await;
+        }
+        Ok::<_, LuaError>(())
+    };
+
+    let stdout_kind = options.stdout.read_kind();
+    let stderr_kind = options.stderr.read_kind();
+    let wait_stages = try_join_all(
+        children
+            .iter_mut()
+            .map(|child| wait_for_stage(child, stdout_kind, stderr_kind)),
+    );
+
+    let ((), results) = try_join!(write_stdin, wait_stages)?;
+
+    let last = results.last().expect("pipeline has at least one stage");
+    let ok = results.iter().all(|r| r.code == 0);
+    let code = last.code;
+    let stdout = lua.create_string(&last.stdout)?;
+
+    let stages = lua.create_table_with_capacity(results.len(), 0)?;
+    for result in results {
+        let stderr = lua.create_string(&result.stderr)?;
+        stages.push(
+            TableBuilder::new(lua.clone())?
+                .with_value("ok", result.code == 0)?
+                .with_value("code", result.code)?
+                .with_value("signal", result.signal)?
+                .with_value("stderr", stderr)?
+                .build_readonly()?,
+        )?;
+    }
+    stages.set_readonly(true);
+
+    TableBuilder::new(lua)?
+        .with_value("ok", ok)?
+        .with_value("code", code)?
+        .with_value("stdout", stdout)?
+        .with_value("stages", stages)?
+        .build_readonly()
+}
+
+async fn spawn_stages(
+    stages: Vec<PipelineStage>,
+    options: &PipelineOptions,
+) -> LuaResult<Vec<Child>> {
+    let stdout_file = options.stdout.open().await.into_lua_err()?;
+    let stderr_file = options.stderr.open().await.into_lua_err()?;
+
+    let count = stages.len();
+    let mut children: Vec<Child> = Vec::with_capacity(count);
+
+    for (index, stage) in stages.into_iter().enumerate() {
+        let is_last = index == count - 1;
+        let program = stage.program.clone();
+        let command = stage.into_command();
+        let context = SpawnContext::new(&command);
+
+        let spawned = async {
+            let stdin = match children.last_mut() {
+                Some(prev) => match prev.stdout.take() {
+                    Some(prev_stdout) => {
+                        prev_stdout.into_stdio().await.map_err(|e| e.to_string())?
+                    }
+                    None => Stdio::null(),
+                },
+                None if options.stdin.is_some() => Stdio::piped(),
+                None => Stdio::null(),
+            };
+            let stdout = if is_last {
+                options
+                    .stdout
+                    .as_stdio(stdout_file.as_ref())
+                    .map_err(|e| e.to_string())?
+            } else {
+                Stdio::piped()
+            };
+            let stderr = options
+                .stderr
+                .as_stdio(stderr_file.as_ref())
+                .map_err(|e| e.to_string())?;
+
+            Command::from(command)
+                .stdin(stdin)
+                .stdout(stdout)
+                .stderr(stderr)
+                .spawn()
+                .map_err(|e| context.reason(&e))
+        }
+        .await;
+
+        match spawned {
+            Ok(child) => children.push(child),
+            Err(e) => {
+                // Stages that were already spawned would otherwise be left running
+                for child in &mut children {
+                    let _ = child.kill();
+                }
+                return Err(LuaError::runtime(format!(
+                    "Failed to spawn pipeline stage #{} ('{program}') - {e}",
+                    index + 1
+                )));
+            }
+        }
+    }
+
+    Ok(children)
+}
+
+async fn wait_for_stage(
+    child: &mut Child,
+    stdout_kind: ProcessSpawnOptionsStdioKind,
+    stderr_kind: ProcessSpawnOptionsStdioKind,
+) -> LuaResult<StageResult> {
+    // Only the last stage has a stdout that was not passed on to another stage
+    let stdout_opt = child.stdout.take();
+    let stdout_kind = if stdout_opt.is_some() {
+        stdout_kind
+    } else {
+        ProcessSpawnOptionsStdioKind::None
+    };
+    let stderr_opt = child.stderr.take();
+
+    let (status, stdout, stderr) = try_join!(
+        async { child.status().await.into_lua_err() },
+        read_with_stdio_kind(stdout_opt, stdout_kind),
+        read_with_stdio_kind(stderr_opt, stderr_kind)
+    )?;
+
+    // Same as in process.exec, a missing exit code means the stage was
+    // terminated by a signal, and we default to 1 only if it wrote errors
+    let code = status.code().unwrap_or(i32::from(!stderr.is_empty()));
+
+    Ok(StageResult {
+        code,
+        signal: exit_signal_name(status),
+        stdout,
+        stderr,
+    })
+}
@@ -0,0 +1,128 @@
+use std::{fs::File, io, path::PathBuf, process::Stdio};
+
+use bstr::BString;
+use mlua::prelude::*;
+
+use crate::options::ProcessSpawnOptionsStdioKind;
+
+/**
+    Where to send an output stream of a pipeline.
+*/
+#[derive(Debug, Clone)]
+pub(crate) enum PipelineOutput {
+    Stdio(ProcessSpawnOptionsStdioKind),
+    File { path: PathBuf, append: bool },
+}
+
+impl PipelineOutput {
+    /**
+        Opens the file that this output redirects to, if any.
+    */
+    pub async fn open(&self) -> io::Result<Option<File>> {
+        let Self::File { path, append } = self else {
+            return Ok(None);
+        };
+        let (path, append) = (path.clone(), *append);
+        blocking::unblock(move || {
+            File::options()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(path)
+                .map(Some)
+        })
+        .await
+    }
+
+    /**
+        Creates the stdio for a child process using this output,
+        given the file that was previously opened using `open`.
+    */
+    pub fn as_stdio(&self, file: Option<&File>) -> io::Result<Stdio> {
+        match (self, file) {
+            (Self::Stdio(kind), _) => Ok(kind.as_stdio()),
+            (Self::File { .. }, Some(file)) => Ok(Stdio::from(file.try_clone()?)),
+            (Self::File { .. }, None) => Ok(Stdio::null()),
+        }
+    }
+
+    /**
+        Returns the stdio kind to use when reading from a child process using this output.
+
+        Files are written to directly by the child process, so there is nothing to read.
+    */
+    pub fn read_kind(&self) -> ProcessSpawnOptionsStdioKind {
+        match self {
+            Self::Stdio(kind) => *kind,
+            Self::File { .. } => ProcessSpawnOptionsStdioKind::None,
+        }
+    }
+}
+
+impl Default for PipelineOutput {
+    fn default() -> Self {
+        Self::Stdio(ProcessSpawnOptionsStdioKind::default())
+    }
+}
+
+impl FromLua for PipelineOutput {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil | LuaValue::String(_) => Ok(Self::Stdio(
+                ProcessSpawnOptionsStdioKind::from_lua(value, lua)?,
+            )),
+            LuaValue::Table(t) => {
+                let Some(path) = t.get::<Option<String>>("file")? else {
+                    return Err(LuaError::runtime(
+                        "Invalid pipeline output - missing 'file'",
+                    ));
+                };
+                let append = t.get::<Option<bool>>("append")?.unwrap_or_default();
+                Ok(Self::File {
+                    path: PathBuf::from(path),
+                    append,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "PipelineOutput".to_string(),
+                message: Some(format!(
+                    "Invalid pipeline output - expected string or table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Options for a pipeline as a whole.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PipelineOptions {
+    pub stdin: Option<Vec<u8>>,
+    pub stdout: PipelineOutput,
+    pub stderr: PipelineOutput,
+}
+
+impl FromLua for PipelineOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                stdin: t.get::<Option<BString>>("stdin")?.map(|s| s.to_vec()),
+                stdout: t.get("stdout")?,
+                stderr: t.get("stderr")?,
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "PipelineOptions".to_string(),
+                message: Some(format!(
+                    "Invalid pipeline options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::process::Command;
+
+use lune_utils::process::ProcessArgs;
+use mlua::prelude::*;
+
+use crate::options::ProcessSpawnOptions;
+
+// Spawn options that do not apply to a single stage of a pipeline
+const UNSUPPORTED_OPTIONS: [&str; 5] = ["stdio", "timeout", "gracePeriod", "pty", "detached"];
+
+/**
+    A single stage (program) in a pipeline.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct PipelineStage {
+    pub program: String,
+    pub args: ProcessArgs,
+    pub options: ProcessSpawnOptions,
+}
+
+impl PipelineStage {
+    pub fn into_command(self) -> Command {
+        self.options.into_command(self.program, self.args)
+    }
+}
+
+impl FromLua for PipelineStage {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = &value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "PipelineStage".to_string(),
+                message: Some(format!(
+                    "Invalid pipeline stage - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let Some(program) = tab.get::<Option<String>>("program")? else {
+            return Err(LuaError::runtime(
+                "Invalid pipeline stage - missing 'program'",
+            ));
+        };
+        let args = tab.get::<ProcessArgs>("args")?;
+
+        // Stages accept the same options as process.exec, other than stdio, which is
+        // instead decided by the pipeline as a whole, and options such as timeouts,
+        // which would also have to apply to the pipeline - these error out instead
+        for name in UNSUPPORTED_OPTIONS {
+            if !tab.get::<LuaValue>(name)?.is_nil() {
+                return Err(LuaError::runtime(format!(
+                    "Invalid pipeline stage - '{name}' can not be used with pipeline stages",
+                )));
+            }
+        }
+        let options = ProcessSpawnOptions::from_lua(value, lua)?;
+
+        Ok(Self {
+            program,
+            args,
+            options,
+        })
+    }
+}
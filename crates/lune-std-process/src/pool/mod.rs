@@ -0,0 +1,122 @@
+use std::{
+    sync::Arc,
+    thread::available_parallelism,
+    time::{Duration, Instant},
+};
+
+use async_lock::Semaphore;
+use futures_lite::prelude::*;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lune_utils::TableBuilder;
+
+use crate::{create::call_callback, exec::spawn_and_exec};
+
+mod options;
+
+pub(crate) use self::options::{PoolCommand, PoolOptions};
+
+type PoolOutcome = Option<(LuaResult<LuaTable>, Duration)>;
+
+/**
+    Runs the given commands with at most `limit` of them running at the same time,
+    and waits for all of them to exit, or for the pool to be cancelled.
+
+    Each command runs as its own task on the scheduler, which lets commands keep
+    running while results for previously completed commands are being handled.
+*/
+pub async fn pool(
+    lua: Lua,
+    commands: Vec<PoolCommand>,
+    options: PoolOptions,
+) -> LuaResult<LuaTable> {
+    let started = Instant::now();
+    let limit = options
+        .limit
+        .unwrap_or_else(|| available_parallelism().map_or(1, Into::into));
+
+    let semaphore = Arc::new(Semaphore::new(limit));
+    let (result_tx, result_rx) = async_channel::unbounded::<(usize, PoolOutcome)>();
+
+    // NOTE: Nothing is ever sent on the cancellation channel, it is only ever closed,
+    // which happens either explicitly or when this function returns / gets dropped
+    let (cancel_tx, cancel_rx) = async_channel::bounded::<()>(1);
+
+    for (index, command) in commands.into_iter().enumerate() {
+        let inner_lua = lua.clone();
+        let semaphore = Arc::clone(&semaphore);
+        let result_tx = result_tx.clone();
+        let cancel_rx = cancel_rx.clone();
+        lua.spawn_local(async move {
+            let cancelled = async move {
+                let _ = cancel_rx.recv().await;
+                None
+            };
+            let run = async move {
+                let _permit = semaphore.acquire_arc().await;
+                let started = Instant::now();
+                let result = spawn_and_exec(
+                    inner_lua,
+                    command.program,
+                    command.args,
+                    command.options,
+                    true,
+                )
+                .await;
+                Some((result, started.elapsed()))
+            };
+            // Cancellation is polled first, so that commands never
+            // get spawned after the pool has already been cancelled
+            let outcome = cancelled.or(run).await;
+            let _ = result_tx.send((index, outcome)).await;
+        });
+    }
+    drop(result_tx);
+
+    let results = lua.create_table()?;
+    let timings = lua.create_table()?;
+    let mut ok = true;
+    let mut cancelled = 0usize;
+    let mut total_time = Duration::ZERO;
+
+    while let Ok((index, outcome)) = result_rx.recv().await {
+        let Some((result, elapsed)) = outcome else {
+            ok = false;
+            cancelled += 1;
+            continue;
+        };
+
+        // Commands that fail to spawn are errors for the pool as a whole, and
+        // returning here also cancels any other commands that are still running
+        let result = result?;
+        let result_ok = result.get::<bool>("ok")?;
+        total_time += elapsed;
+        let elapsed = elapsed.as_secs_f64();
+
+        results.raw_set(index + 1, &result)?;
+        timings.raw_set(index + 1, elapsed)?;
+
+        let mut cancel = options.fail_fast && !result_ok;
+        if let Some(callback) = &options.on_result
+            && !call_callback(&lua, callback, (index + 1, result, elapsed)).await?
+        {
+            cancel = true;
+        }
+        if cancel {
+            cancel_tx.close();
+        }
+
+        ok &= result_ok;
+    }
+
+    TableBuilder::new(lua)?
+        .with_value("ok", ok)?
+        .with_value("cancelled", cancelled)?
+        .with_value("results", results)?
+        .with_value("timings", timings)?
+        .with_value("elapsed", started.elapsed().as_secs_f64())?
+        .with_value("totalTime", total_time.as_secs_f64())?
+        .build_readonly()
+}
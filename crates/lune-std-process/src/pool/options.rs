@@ -0,0 +1,85 @@
+use mlua::prelude::*;
+
+use lune_utils::process::ProcessArgs;
+
+use crate::options::ProcessSpawnOptions;
+
+/**
+    A single command to run in a process pool.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct PoolCommand {
+    pub program: String,
+    pub args: ProcessArgs,
+    pub options: ProcessSpawnOptions,
+}
+
+impl FromLua for PoolCommand {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = &value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "PoolCommand".to_string(),
+                message: Some(format!(
+                    "Invalid pool command - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let Some(program) = tab.get::<Option<String>>("program")? else {
+            return Err(LuaError::runtime(
+                "Invalid pool command - missing 'program'",
+            ));
+        };
+        let args = tab.get::<ProcessArgs>("args")?;
+
+        // Commands accept the exact same options as process.exec
+        let options = ProcessSpawnOptions::from_lua(value, lua)?;
+
+        Ok(Self {
+            program,
+            args,
+            options,
+        })
+    }
+}
+
+/**
+    Options for a process pool as a whole.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PoolOptions {
+    pub limit: Option<usize>,
+    pub fail_fast: bool,
+    pub on_result: Option<LuaFunction>,
+}
+
+impl FromLua for PoolOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let limit = t.get::<Option<usize>>("limit")?;
+                if limit == Some(0) {
+                    return Err(LuaError::runtime(
+                        "Invalid pool options - 'limit' must be at least 1",
+                    ));
+                }
+                Ok(Self {
+                    limit,
+                    fail_fast: t.get::<Option<bool>>("failFast")?.unwrap_or_default(),
+                    on_result: t.get("onResult")?,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "PoolOptions".to_string(),
+                message: Some(format!(
+                    "Invalid pool options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
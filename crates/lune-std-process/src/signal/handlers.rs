@@ -0,0 +1,306 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io,
+    rc::Rc,
+};
+
+use async_signal::{Signal as OsSignal, Signals};
+use futures_lite::{future, prelude::*};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+
+use super::Signal;
+
+/**
+    Signals that terminate the process by default, which are listened to
+    when the runtime starts, so that it may exit gracefully instead.
+*/
+const TERMINATING_SIGNALS: [Signal; 3] = [Signal::Int, Signal::Term, Signal::Hup];
+
+#[derive(Debug, Default)]
+struct SignalHandlersInner {
+    next_id: u64,
+    handlers: HashMap<Signal, Vec<(u64, LuaFunction)>>,
+    listening: HashSet<Signal>,
+    waking: HashSet<Signal>,
+}
+
+/**
+    Handlers for signals sent to the current process, stored in Lua app data.
+
+    Listening for a signal starts when its first handler is connected, or when the
+    runtime starts for signals that terminate the process, and from then on the
+    default action for that signal is emulated whenever it is received without
+    any handlers connected - see [`default_action`].
+*/
+#[derive(Debug, Clone, Default)]
+pub struct SignalHandlers {
+    inner: Rc<RefCell<SignalHandlersInner>>,
+}
+
+impl SignalHandlers {
+    fn from_lua_app_data(lua: &Lua) -> Self {
+        let existing = lua.app_data_ref::<Self>().map(|h| h.clone());
+        existing.unwrap_or_else(|| {
+            let handlers = Self::default();
+            lua.set_app_data(handlers.clone());
+            handlers
+        })
+    }
+
+    /**
+        Connects a handler for the given signal, returning a function that disconnects it.
+
+        # Errors
+
+        Errors if the signal can not be handled on the current platform.
+    */
+    pub fn connect(lua: &Lua, signal: Signal, callback: LuaFunction) -> LuaResult<LuaFunction> {
+        let os_signal = os_signal(signal).into_lua_err()?;
+        let this = Self::from_lua_app_data(lua);
+
+        if !this.inner.borrow().listening.contains(&signal) {
+            this.listen(lua, signal, os_signal).into_lua_err()?;
+        }
+        this.wake_on(lua, signal);
+
+        let id = {
+            let mut inner = this.inner.borrow_mut();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            inner
+                .handlers
+                .entry(signal)
+                .or_default()
+                .push((id, callback));
+            id
+        };
+
+        lua.create_function(move |_, (): ()| {
+            if let Some(handlers) = this.inner.borrow_mut().handlers.get_mut(&signal) {
+                handlers.retain(|(handler_id, _)| *handler_id != id);
+            }
+            Ok(())
+        })
+    }
+
+    /**
+        Starts listening for signals that would normally terminate the process,
+        running their default action unless any handlers have been connected.
+
+        Signals that are not supported on the current platform are skipped.
+
+        # Errors
+
+        Errors if listening for any of the signals fails.
+    */
+    pub fn listen_for_defaults(lua: &Lua) -> LuaResult<()> {
+        let this = Self::from_lua_app_data(lua);
+        for signal in TERMINATING_SIGNALS {
+            let Ok(os_signal) = os_signal(signal) else {
+                continue;
+            };
+            if !this.inner.borrow().listening.contains(&signal) {
+                this.listen(lua, signal, os_signal).into_lua_err()?;
+            }
+        }
+        #[cfg(unix)]
+        force_exit::register().into_lua_err()?;
+        Ok(())
+    }
+
+    fn listen(&self, lua: &Lua, signal: Signal, os_signal: OsSignal) -> io::Result<()> {
+        let mut signals = Signals::new([os_signal])?;
+        self.inner.borrow_mut().listening.insert(signal);
+
+        // NOTE: Listening happens in the background, so that it never
+        // prevents the scheduler from completing on its own - see below
+        let guard = ListeningGuard {
+            handlers: self.clone(),
+            signal,
+        };
+        let lua_inner = lua.clone();
+        lua.spawn_local_background(async move {
+            while let Some(Ok(_)) = signals.next().await {
+                guard.handlers.dispatch(&lua_inner, signal);
+            }
+        });
+
+        Ok(())
+    }
+
+    /**
+        Marks the scheduler as having a listener for the given signal, once handlers
+        have been connected for it, since they may then wake up threads that are
+        blocked on each other, and those threads should not count as deadlocked.
+    */
+    fn wake_on(&self, lua: &Lua, signal: Signal) {
+        if self.inner.borrow_mut().waking.insert(signal) {
+            lua.spawn_local_listener(future::pending());
+        }
+    }
+
+    fn dispatch(&self, lua: &Lua, signal: Signal) {
+        let callbacks = self
+            .inner
+            .borrow()
+            .handlers
+            .get(&signal)
+            .map(|handlers| {
+                handlers
+                    .iter()
+                    .map(|(_, callback)| callback.clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if callbacks.is_empty() {
+            default_action(lua, signal);
+        } else {
+            #[cfg(unix)]
+            force_exit::handled(signal);
+            for callback in callbacks {
+                // Will only error when out of memory
+                let _ = lua.push_thread_front(callback, signal.name());
+            }
+        }
+    }
+}
+
+/**
+    Marks a signal as no longer being listened to, once the
+    background task listening for it has been dropped.
+*/
+struct ListeningGuard {
+    handlers: SignalHandlers,
+    signal: Signal,
+}
+
+impl Drop for ListeningGuard {
+    fn drop(&mut self) {
+        let mut inner = self.handlers.inner.borrow_mut();
+        inner.listening.remove(&self.signal);
+        inner.waking.remove(&self.signal);
+    }
+}
+
+/**
+    Runs the default action for a signal received without any handlers connected.
+
+    Signals that would normally terminate the process instead set the exit
+    code of the scheduler, letting the runtime exit gracefully and run any
+    cleanup, instead of being terminated immediately by the OS.
+*/
+fn default_action(lua: &Lua, signal: Signal) {
+    match signal {
+        Signal::Cont | Signal::Winch => {}
+        #[cfg(unix)]
+        Signal::Tstp => {
+            // SAFETY: raise has no memory safety requirements, and
+            // only sends a signal to the current process (stopping it)
+            unsafe {
+                libc::raise(libc::SIGSTOP);
+            }
+        }
+        _ => lua.set_exit_code(exit_code(signal)),
+    }
+}
+
+/**
+    Forceful termination for signals that the scheduler is unable to handle,
+    such as when a Lua thread keeps running without ever yielding.
+
+    The first terminating signal is left for the scheduler to handle, but if
+    another one is received before that happens, the process is terminated
+    immediately, same as it would have been without listening for signals.
+*/
+#[cfg(unix)]
+mod force_exit {
+    use std::{
+        io,
+        sync::{
+            Once,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use super::{Signal, TERMINATING_SIGNALS, exit_code};
+
+    static REGISTER: Once = Once::new();
+    static UNHANDLED: AtomicUsize = AtomicUsize::new(0);
+
+    pub fn register() -> io::Result<()> {
+        let mut result = Ok(());
+        REGISTER.call_once(|| {
+            for signal in TERMINATING_SIGNALS {
+                let code = i32::from(exit_code(signal));
+                // SAFETY: The action only uses atomics and _exit,
+                // which are both safe to use within a signal handler
+                let registered = unsafe {
+                    signal_hook_registry::register(signal.as_raw(), move || {
+                        if UNHANDLED.fetch_add(1, Ordering::SeqCst) > 0 {
+                            libc::_exit(code);
+                        }
+                    })
+                };
+                if let Err(e) = registered {
+                    result = Err(e);
+                    return;
+                }
+            }
+        });
+        result
+    }
+
+    pub fn handled(signal: Signal) {
+        if TERMINATING_SIGNALS.contains(&signal) {
+            UNHANDLED.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+fn exit_code(signal: Signal) -> u8 {
+    #[cfg(unix)]
+    {
+        u8::try_from(128 + signal.as_raw()).unwrap_or(u8::MAX)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = signal;
+        130
+    }
+}
+
+fn os_signal(signal: Signal) -> io::Result<OsSignal> {
+    #[cfg(unix)]
+    {
+        Ok(match signal {
+            Signal::Hup => OsSignal::Hup,
+            Signal::Int => OsSignal::Int,
+            Signal::Quit => OsSignal::Quit,
+            Signal::Usr1 => OsSignal::Usr1,
+            Signal::Usr2 => OsSignal::Usr2,
+            Signal::Pipe => OsSignal::Pipe,
+            Signal::Alrm => OsSignal::Alarm,
+            Signal::Term => OsSignal::Term,
+            Signal::Cont => OsSignal::Cont,
+            Signal::Tstp => OsSignal::Tstp,
+            Signal::Winch => OsSignal::Winch,
+            Signal::Kill | Signal::Stop => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Signal {signal} can not be handled"),
+                ));
+            }
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        match signal {
+            Signal::Int => Ok(OsSignal::Int),
+            _ => Err(super::unsupported(signal)),
+        }
+    }
+}
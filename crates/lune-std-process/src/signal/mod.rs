@@ -0,0 +1,236 @@
+use std::{fmt, io, process::ExitStatus, str::FromStr};
+
+use async_process::Child;
+use mlua::prelude::*;
+
+mod handlers;
+
+pub use self::handlers::SignalHandlers;
+
+/**
+    A signal that can be sent to a child process, or handled by the current process.
+
+    On platforms other than unix, only `SIGKILL` and `SIGTERM` are supported for child processes,
+    and both forcefully terminate the child process. Only `SIGINT` may be handled on those platforms.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    Hup,
+    Int,
+    Quit,
+    Kill,
+    Usr1,
+    Usr2,
+    Pipe,
+    Alrm,
+    Term,
+    Cont,
+    Stop,
+    Tstp,
+    Winch,
+}
+
+impl Signal {
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Hup,
+            Self::Int,
+            Self::Quit,
+            Self::Kill,
+            Self::Usr1,
+            Self::Usr2,
+            Self::Pipe,
+            Self::Alrm,
+            Self::Term,
+            Self::Cont,
+            Self::Stop,
+            Self::Tstp,
+            Self::Winch,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hup => "SIGHUP",
+            Self::Int => "SIGINT",
+            Self::Quit => "SIGQUIT",
+            Self::Kill => "SIGKILL",
+            Self::Usr1 => "SIGUSR1",
+            Self::Usr2 => "SIGUSR2",
+            Self::Pipe => "SIGPIPE",
+            Self::Alrm => "SIGALRM",
+            Self::Term => "SIGTERM",
+            Self::Cont => "SIGCONT",
+            Self::Stop => "SIGSTOP",
+            Self::Tstp => "SIGTSTP",
+            Self::Winch => "SIGWINCH",
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn as_raw(self) -> i32 {
+        match self {
+            Self::Hup => libc::SIGHUP,
+            Self::Int => libc::SIGINT,
+            Self::Quit => libc::SIGQUIT,
+            Self::Kill => libc::SIGKILL,
+            Self::Usr1 => libc::SIGUSR1,
+            Self::Usr2 => libc::SIGUSR2,
+            Self::Pipe => libc::SIGPIPE,
+            Self::Alrm => libc::SIGALRM,
+            Self::Term => libc::SIGTERM,
+            Self::Cont => libc::SIGCONT,
+            Self::Stop => libc::SIGSTOP,
+            Self::Tstp => libc::SIGTSTP,
+            Self::Winch => libc::SIGWINCH,
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn from_raw(raw: i32) -> Option<Self> {
+        Self::all().iter().copied().find(|s| s.as_raw() == raw)
+    }
+
+    /**
+        Sends this signal to the process with the given id.
+
+        # Errors
+
+        Errors if the signal could not be sent, or if it is not
+        supported on the current platform.
+    */
+    #[cfg(unix)]
+    pub fn send_to_pid(self, pid: u32) -> io::Result<()> {
+        let pid = libc::pid_t::try_from(pid).map_err(io::Error::other)?;
+        // SAFETY: kill has no memory safety requirements, and only
+        // sends a signal to the process, or returns an error code
+        if unsafe { libc::kill(pid, self.as_raw()) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /**
+        Sends this signal to the given child process.
+
+        # Errors
+
+        Errors if the signal could not be sent, or if it is not
+        supported on the current platform.
+    */
+    pub fn send(self, child: &mut Child) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            if self == Self::Kill {
+                child.kill()
+            } else {
+                self.send_to_pid(child.id())
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            match self {
+                Self::Kill | Self::Term => child.kill(),
+                _ => Err(unsupported(self)),
+            }
+        }
+    }
+
+    /**
+        Sends this signal to the process group led by the given child process,
+        including any processes that it has spawned in turn, falling back to
+        only sending it to the child process if it does not lead a group.
+
+        # Errors
+
+        Errors if the signal could not be sent, or if it is not
+        supported on the current platform.
+    */
+    pub fn send_to_group(self, child: &mut Child) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            let pgid = libc::pid_t::try_from(child.id()).map_err(io::Error::other)?;
+            // SAFETY: kill has no memory safety requirements, and only
+            // sends a signal to the process group, or returns an error code
+            if unsafe { libc::kill(-pgid, self.as_raw()) } == 0 {
+                return Ok(());
+            }
+        }
+        self.send(child)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn unsupported(signal: Signal) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Signal {signal} is not supported on this platform"),
+    )
+}
+
+/**
+    Returns the name of the signal that terminated a process, if any.
+
+    Always returns `None` on platforms other than unix.
+*/
+pub fn exit_signal_name(status: ExitStatus) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal().map(|raw| match Signal::from_raw(raw) {
+            Some(signal) => signal.name().to_string(),
+            None => format!("SIG{raw}"),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Signal {
+    type Err = LuaError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.trim().to_ascii_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        Self::all()
+            .iter()
+            .copied()
+            .find(|signal| &signal.name()[3..] == name)
+            .ok_or_else(|| {
+                LuaError::RuntimeError(format!(
+                    "Invalid signal - got '{}', expected one of {}",
+                    s,
+                    Self::all()
+                        .iter()
+                        .map(|k| format!("'{k}'"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+impl FromLua for Signal {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::String(s) => s.to_str()?.parse(),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Signal".to_string(),
+                message: Some(format!(
+                    "Invalid signal - expected string, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    env::{current_dir, split_paths, var_os},
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use mlua::prelude::*;
+
+/**
+    Searches for an executable program, same as the `which` command.
+
+    Names containing a path separator are resolved relative to the given
+    directory, while any other names are searched for in the given `PATH`.
+
+    On Windows, names without an extension are also searched
+    for with each of the extensions listed in `PATHEXT`.
+*/
+pub fn which(name: &OsStr, path_env: Option<&OsStr>, cwd: &Path) -> Option<PathBuf> {
+    find(name, path_env, cwd, is_executable)
+}
+
+fn find(
+    name: &OsStr,
+    path_env: Option<&OsStr>,
+    cwd: &Path,
+    predicate: fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+
+    let path = Path::new(name);
+    if path.components().count() > 1 || path.is_absolute() {
+        return candidates(&cwd.join(path))
+            .into_iter()
+            .find(|p| predicate(p));
+    }
+
+    split_paths(path_env?)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| candidates(&cwd.join(dir).join(name)))
+        .find(|p| predicate(p))
+}
+
+#[cfg(unix)]
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    vec![path.to_path_buf()]
+}
+
+#[cfg(not(unix))]
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    let path_ext = var_os("PATHEXT").unwrap_or_else(|| OsString::from(".COM;.EXE;.BAT;.CMD"));
+    let path_ext = path_ext.to_string_lossy();
+    let extensions = path_ext
+        .split(';')
+        .filter(|ext| !ext.is_empty())
+        .collect::<Vec<_>>();
+
+    let has_extension = path.extension().is_some_and(|ext| {
+        let ext = format!(".{}", ext.to_string_lossy());
+        extensions.iter().any(|e| e.eq_ignore_ascii_case(&ext))
+    });
+
+    if has_extension {
+        vec![path.to_path_buf()]
+    } else {
+        extensions
+            .iter()
+            .map(|ext| {
+                let mut with_ext = path.as_os_str().to_os_string();
+                with_ext.push(ext);
+                PathBuf::from(with_ext)
+            })
+            .collect()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn is_file(path: &Path) -> bool {
+    path.is_file()
+}
+
+/**
+    Information about a program that is about to be spawned,
+    used to describe why spawning it failed, if it does.
+*/
+#[derive(Debug, Clone)]
+pub struct SpawnContext {
+    program: OsString,
+    cwd: PathBuf,
+    path_env: Option<OsString>,
+}
+
+impl SpawnContext {
+    pub fn new(command: &Command) -> Self {
+        let cwd = current_dir().unwrap_or_default();
+        let cwd = match command.get_current_dir() {
+            Some(dir) => cwd.join(dir),
+            None => cwd,
+        };
+
+        // NOTE: The program is looked up using the PATH given to the child, if any
+        let path_env = command
+            .get_envs()
+            .find(|(key, _)| *key == "PATH")
+            .map_or_else(
+                || var_os("PATH"),
+                |(_, value)| value.map(OsStr::to_os_string),
+            );
+
+        Self {
+            program: command.get_program().to_os_string(),
+            cwd,
+            path_env,
+        }
+    }
+
+    /**
+        Describes why spawning the program failed, given the resulting error.
+    */
+    pub fn reason(&self, error: &io::Error) -> String {
+        let has_separator = Path::new(&self.program).components().count() > 1;
+        let cwd = self.cwd.display();
+        match error.kind() {
+            io::ErrorKind::NotFound => {
+                match find(&self.program, self.path_env.as_deref(), &self.cwd, is_file) {
+                    Some(path) => format!(
+                        "program was found at '{}', but could not be run (cwd: '{cwd}')",
+                        path.display()
+                    ),
+                    None if has_separator => format!("program not found (cwd: '{cwd}')"),
+                    None => format!("program not found in PATH (cwd: '{cwd}')"),
+                }
+            }
+            io::ErrorKind::PermissionDenied => {
+                match find(&self.program, self.path_env.as_deref(), &self.cwd, is_file) {
+                    Some(path) => format!(
+                        "program at '{}' is not executable (cwd: '{cwd}')",
+                        path.display()
+                    ),
+                    None => format!("program is not executable (cwd: '{cwd}')"),
+                }
+            }
+            _ => format!("{error} (cwd: '{cwd}')"),
+        }
+    }
+
+    /**
+        Creates an error describing why spawning the program failed.
+    */
+    pub fn error(&self, error: &io::Error) -> LuaError {
+        LuaError::runtime(format!(
+            "Failed to spawn '{}' - {}",
+            self.program.to_string_lossy(),
+            self.reason(error)
+        ))
+    }
+}
@@ -1,15 +1,31 @@
 #![allow(clippy::cargo_common_metadata)]
 
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use async_io::Timer;
 use futures_lite::future::yield_now;
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::Functions;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt};
 
 use lune_utils::TableBuilder;
 
+mod options;
+mod scope;
+mod stats;
+mod sync;
+mod threads;
+mod timer;
+mod worker;
+
+pub use self::worker::{WorkerContext, WorkerSpawner};
+
+use self::{
+    options::SpawnOptions,
+    scope::Scopes,
+    sync::{Channel, Event, Mutex, Semaphore},
+    threads::ThreadResults,
+};
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 /**
@@ -29,13 +45,48 @@ pub fn typedefs() -> String {
 */
 pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     let fns = Functions::new(lua.clone())?;
+    let scopes = Scopes::new(fns.cancel);
+
+    // Wrap spawn & defer to track the results of spawned threads, so that they may be
+    // awaited, to add them to the scope of the thread that spawned them, if any, and
+    // to apply any options given, such as the priority of the thread
+    let task_spawn = create_tracked(&lua, &scopes, fns.spawn)?;
+    let task_defer = create_tracked(&lua, &scopes, fns.defer)?;
+
+    // Wrap cancel to also cancel any scopes that the thread owns or is waiting for
+    let task_cancel = {
+        let scopes = scopes.clone();
+        lua.create_function(move |_, thread: LuaThread| scopes.cancel(&thread))?
+    };
+
+    // Create scope & timeout functions
+    let task_scope = {
+        let scopes = scopes.clone();
+        lua.create_async_function(move |lua, (function, args): (LuaFunction, LuaMultiValue)| {
+            let scopes = scopes.clone();
+            async move { scopes.run(&lua, function, args, None).await }
+        })?
+    };
+    let task_timeout = lua.create_async_function(
+        move |lua, (secs, function, args): (f64, LuaFunction, LuaMultiValue)| {
+            let scopes = scopes.clone();
+            async move {
+                let duration = Duration::try_from_secs_f64(secs).map_err(|_| {
+                    LuaError::runtime(format!(
+                        "Invalid timeout - expected a non-negative number, got {secs}"
+                    ))
+                })?;
+                scopes.run(&lua, function, args, Some(duration)).await
+            }
+        },
+    )?;
 
     // Create wait & delay functions
     let task_wait = lua.create_async_function(wait)?;
     let task_delay_env = TableBuilder::new(lua.clone())?
-        .with_value("select", lua.globals().get::<LuaFunction>("select")?)?
-        .with_value("spawn", fns.spawn.clone())?
-        .with_value("defer", fns.defer.clone())?
+        .with_value("type", lua.globals().get::<LuaFunction>("type")?)?
+        .with_value("spawn", task_spawn.clone())?
+        .with_value("defer", task_defer.clone())?
         .with_value("wait", task_wait.clone())?
         .build_readonly()?;
     let task_delay = lua
@@ -44,22 +95,131 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .set_environment(task_delay_env)
         .into_function()?;
 
+    // Create await functions, which raise the original error values of threads
+    let results = ThreadResults::get(&lua)?;
+    let task_all = results.raising(lua.create_async_function(threads::await_all)?)?;
+    let task_any = results.raising(lua.create_async_function(threads::await_any)?)?;
+    let task_await = results.raising(lua.create_async_function(threads::await_thread)?)?;
+    let task_race = results.raising(lua.create_async_function(threads::await_race)?)?;
+
+    // Create the parent port, if this is a worker - this will be nil otherwise
+    let task_parent = worker::parent(&lua);
+
     TableBuilder::new(lua)?
-        .with_value("cancel", fns.cancel)?
-        .with_value("defer", fns.defer)?
+        .with_async_function("advance", advance)?
+        .with_value("all", task_all)?
+        .with_value("any", task_any)?
+        .with_value("await", task_await)?
+        .with_value("cancel", task_cancel)?
+        .with_function("channel", Channel::new)?
+        .with_function("clock", timer::clock)?
+        .with_value("defer", task_defer)?
         .with_value("delay", task_delay)?
-        .with_value("spawn", fns.spawn)?
+        .with_function("dump", stats::dump)?
+        .with_function("event", Event::new)?
+        .with_function("getContext", get_context)?
+        .with_function("interval", timer::Timer::new_interval)?
+        .with_function("mutex", Mutex::new)?
+        .with_value("parent", task_parent)?
+        .with_value("race", task_race)?
+        .with_value("scope", task_scope)?
+        .with_function("semaphore", Semaphore::new)?
+        .with_function("setContext", set_context)?
+        .with_value("spawn", task_spawn)?
+        .with_function("stats", stats::stats)?
+        .with_value("timeout", task_timeout)?
+        .with_function("timer", timer::Timer::new_once)?
         .with_value("wait", task_wait)?
+        .with_function("worker", worker::create)?
         .build_readonly()
 }
 
+/*
+    NOTE: Delayed functions run directly in the deferred thread, instead of
+    being spawned in a new one, so that the thread returned by task.delay
+    may be awaited for the results of the function, or cancelled entirely
+*/
 const DELAY_IMPL_LUA: &str = r"
-return defer(function(...)
-    wait(select(1, ...))
-    spawn(select(2, ...))
+return defer(function(duration, functionOrThread, ...)
+    wait(duration)
+    if type(functionOrThread) == 'function' then
+        return functionOrThread(...)
+    else
+        spawn(functionOrThread, ...)
+    end
 end, ...)
 ";
 
+fn create_tracked(lua: &Lua, scopes: &Scopes, inner: LuaFunction) -> LuaResult<LuaFunction> {
+    let scopes = scopes.clone();
+    let results = ThreadResults::get(lua)?;
+    lua.create_function(move |lua, (tof, mut args): (LuaValue, LuaMultiValue)| {
+        // Options may optionally be given before the function or thread
+        let (options, tof) = match tof {
+            LuaValue::Table(_) => (
+                SpawnOptions::from_lua(tof, lua)?,
+                args.pop_front().unwrap_or(LuaNil),
+            ),
+            tof => (SpawnOptions::default(), tof),
+        };
+
+        // Functions store their own results, but threads must be watched for them
+        let (thread, watched) = match tof {
+            LuaValue::Thread(thread) => {
+                let watched = results.track(lua, &thread)?;
+                (thread, watched)
+            }
+            LuaValue::Function(function) => {
+                (results.create_thread(lua, function, &mut args)?, false)
+            }
+            value => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "thread".to_string(),
+                    message: Some("Expected thread or function".to_string()),
+                });
+            }
+        };
+
+        if let Some(priority) = options.priority {
+            lua.set_thread_priority(&thread, priority)?;
+        }
+
+        scopes.adopt(lua, &thread);
+        let thread = inner.call::<LuaThread>((thread, args))?;
+        if watched {
+            results.watch(lua, thread.clone())?;
+        }
+
+        Ok(thread)
+    })
+}
+
+async fn advance(lua: Lua, secs: f64) -> LuaResult<()> {
+    let clock = lua.scheduler_clock();
+    if !clock.is_virtual() {
+        return Err(LuaError::runtime(
+            "Time can only be advanced manually when using virtual time",
+        ));
+    }
+    let duration = Duration::try_from_secs_f64(secs).map_err(|_| {
+        LuaError::runtime(format!(
+            "Invalid duration - expected a non-negative number, got {secs}"
+        ))
+    })?;
+    clock.advance(duration).await;
+    Ok(())
+}
+
+fn get_context(lua: &Lua, key: String) -> LuaResult<LuaValue> {
+    Ok(lua.current_context().get(&key)?.unwrap_or(LuaNil))
+}
+
+fn set_context(lua: &Lua, (key, value): (String, LuaValue)) -> LuaResult<()> {
+    let context = lua.current_context().with(lua, &key, value)?;
+    lua.set_thread_context(&lua.current_thread(), context)
+}
+
 async fn wait(lua: Lua, secs: Option<f64>) -> LuaResult<f64> {
     // NOTE: We must guarantee that the task.wait API always yields
     // from a lua perspective, even if sleep/timer completes instantly
@@ -67,7 +227,7 @@ async fn wait(lua: Lua, secs: Option<f64>) -> LuaResult<f64> {
     wait_inner(lua, secs).await
 }
 
-async fn wait_inner(_: Lua, secs: Option<f64>) -> LuaResult<f64> {
+async fn wait_inner(lua: Lua, secs: Option<f64>) -> LuaResult<f64> {
     // One millisecond is a reasonable minimum sleep duration,
     // anything lower than this runs the risk of completing the
     // the below timer instantly, without giving control to the OS ...
@@ -83,8 +243,10 @@ async fn wait_inner(_: Lua, secs: Option<f64>) -> LuaResult<f64> {
     // coroutine that calls this sleep function always yields,
     // even if the timer is able to complete without doing so
     yield_now().await;
-    // We may then sleep as normal
-    let before = Instant::now();
-    let after = Timer::after(duration).await;
-    Ok((after - before).as_secs_f64())
+    // We may then sleep as normal, using the clock of the
+    // scheduler, which may be using virtual time instead
+    let clock = lua.scheduler_clock();
+    let before = clock.now();
+    clock.sleep(duration).await;
+    Ok((clock.now() - before).as_secs_f64())
 }
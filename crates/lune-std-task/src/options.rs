@@ -0,0 +1,36 @@
+use mlua::prelude::*;
+use mlua_luau_scheduler::Priority;
+
+/**
+    Options that may be given to `task.spawn` and `task.defer`, as a table before the function or thread.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SpawnOptions {
+    pub priority: Option<Priority>,
+}
+
+impl FromLua for SpawnOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(table) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "SpawnOptions".to_string(),
+                message: Some("Expected options to be a table".to_string()),
+            });
+        };
+
+        let priority = match table.get::<Option<String>>("priority")?.as_deref() {
+            None => None,
+            Some("low") => Some(Priority::Low),
+            Some("normal") => Some(Priority::Normal),
+            Some("high") => Some(Priority::High),
+            Some(other) => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid priority - expected 'low', 'normal', or 'high', got '{other}'"
+                )));
+            }
+        };
+
+        Ok(Self { priority })
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    mem,
+    rc::Rc,
+    time::Duration,
+};
+
+use futures_lite::{future::pending, prelude::*};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, OnceEvent, ThreadId};
+
+// The least number of threads in a scope before any finished threads are removed from it
+const MIN_PRUNE_AT: usize = 64;
+
+/**
+    A cancellation scope, containing its body thread and all threads spawned within it.
+*/
+struct Scope {
+    body: LuaThread,
+    threads: RefCell<Vec<LuaThread>>,
+    // The number of threads in the scope at which finished threads are removed next
+    prune_at: Cell<usize>,
+    cancelled: OnceEvent,
+}
+
+enum ScopeOutcome {
+    Completed(LuaResult<LuaMultiValue>),
+    Cancelled,
+    TimedOut,
+}
+
+#[derive(Default)]
+struct ScopesInner {
+    // The scope that each thread was spawned within
+    threads: HashMap<ThreadId, Rc<Scope>>,
+    // The scope that each thread is currently waiting for
+    waiting: HashMap<ThreadId, Rc<Scope>>,
+}
+
+/**
+    Cancellation scopes created using `task.scope` and `task.timeout`.
+
+    Threads spawned using the task library from within a scope are added to that scope,
+    and all threads in a scope are cancelled once the scope is left or cancelled.
+*/
+#[derive(Clone)]
+pub(crate) struct Scopes {
+    cancel: LuaFunction,
+    inner: Rc<RefCell<ScopesInner>>,
+}
+
+impl Scopes {
+    pub fn new(cancel: LuaFunction) -> Self {
+        Self {
+            cancel,
+            inner: Rc::new(RefCell::new(ScopesInner::default())),
+        }
+    }
+
+    /**
+        Adds a thread that is about to be spawned to the scope of the current thread, if any.
+    */
+    pub fn adopt(&self, lua: &Lua, thread: &LuaThread) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.threads.is_empty() {
+            return;
+        }
+
+        let current = ThreadId::from(&lua.current_thread());
+        let Some(scope) = inner.threads.get(&current).cloned() else {
+            return;
+        };
+
+        // NOTE: Scopes may be long-lived and spawn lots of short-lived threads, so we
+        // remove finished threads now and then, to not keep them around until the scope
+        // is left - doubling the limit each time to keep this cheap, amortized over spawns
+        let mut threads = scope.threads.borrow_mut();
+        if threads.len() >= scope.prune_at.get() {
+            threads.retain(|thread| {
+                let finished = matches!(
+                    thread.status(),
+                    LuaThreadStatus::Finished | LuaThreadStatus::Error
+                );
+                if finished {
+                    inner.threads.remove(&ThreadId::from(thread));
+                }
+                !finished
+            });
+            scope.prune_at.set((threads.len() * 2).max(MIN_PRUNE_AT));
+        }
+        threads.push(thread.clone());
+        drop(threads);
+
+        inner.threads.insert(ThreadId::from(thread), scope);
+    }
+
+    /**
+        Cancels the given thread, as well as the scope it is currently waiting for, if any.
+
+        Cancelling the body thread of a scope also cancels the scope itself.
+    */
+    pub fn cancel(&self, thread: &LuaThread) -> LuaResult<()> {
+        self.cancel.call::<()>(thread)?;
+
+        let id = ThreadId::from(thread);
+        let (waiting, owned) = {
+            let mut inner = self.inner.borrow_mut();
+            let waiting = inner.waiting.remove(&id);
+            let owned = inner
+                .threads
+                .get(&id)
+                .filter(|scope| scope.body == *thread)
+                .cloned();
+            (waiting, owned)
+        };
+
+        if let Some(scope) = waiting {
+            self.cancel_scope(&scope)?;
+        }
+        if let Some(scope) = owned {
+            self.cancel_scope(&scope)?;
+        }
+        Ok(())
+    }
+
+    fn cancel_scope(&self, scope: &Scope) -> LuaResult<()> {
+        scope.cancelled.notify();
+
+        let threads = mem::take(&mut *scope.threads.borrow_mut());
+        {
+            let mut inner = self.inner.borrow_mut();
+            for thread in &threads {
+                inner.threads.remove(&ThreadId::from(thread));
+            }
+        }
+
+        for thread in threads {
+            if thread.status() == LuaThreadStatus::Resumable {
+                self.cancel(&thread)?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+        Runs the given function in a new scope, waiting for it to complete, and
+        then cancelling any threads that were spawned within the scope and are
+        still running. The scope is also cancelled if the timeout is reached.
+    */
+    pub async fn run(
+        &self,
+        lua: &Lua,
+        function: LuaFunction,
+        args: LuaMultiValue,
+        timeout: Option<Duration>,
+    ) -> LuaResult<LuaMultiValue> {
+        let caller = ThreadId::from(&lua.current_thread());
+        let body = lua.create_thread(function)?;
+        lua.set_thread_context(&body, lua.current_context())?;
+        let scope = Rc::new(Scope {
+            body: body.clone(),
+            threads: RefCell::new(vec![body.clone()]),
+            prune_at: Cell::new(MIN_PRUNE_AT),
+            cancelled: OnceEvent::new(),
+        });
+
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner
+                .threads
+                .insert(ThreadId::from(&body), Rc::clone(&scope));
+            inner.waiting.insert(caller, Rc::clone(&scope));
+        }
+
+        // NOTE: The body runs directly as part of this function, instead of
+        // being spawned, so that its errors are only raised in the caller
+        let completed = body.into_async::<LuaMultiValue>(args)?;
+        let completed = async { ScopeOutcome::Completed(completed.await) };
+        let cancelled = scope.cancelled.listen();
+        let cancelled = async {
+            cancelled.await;
+            ScopeOutcome::Cancelled
+        };
+        let clock = lua.scheduler_clock();
+        let timed_out = async {
+            match timeout {
+                Some(duration) => clock.sleep(duration).await,
+                None => pending().await,
+            }
+            ScopeOutcome::TimedOut
+        };
+        let outcome = completed.or(cancelled).or(timed_out).await;
+
+        // Leaving the scope, for any reason, cancels everything still running within it
+        self.inner.borrow_mut().waiting.remove(&caller);
+        self.cancel_scope(&scope)?;
+
+        match outcome {
+            ScopeOutcome::Completed(result) => result,
+            ScopeOutcome::Cancelled => Err(LuaError::runtime("Scope was cancelled")),
+            ScopeOutcome::TimedOut => Err(LuaError::runtime(format!(
+                "Timed out after {} seconds",
+                timeout.unwrap_or_default().as_secs_f64()
+            ))),
+        }
+    }
+}
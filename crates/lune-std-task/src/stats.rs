@@ -0,0 +1,84 @@
+use std::fmt::Write;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, Status};
+
+use lune_utils::TableBuilder;
+
+/**
+    Returns a table describing the current state of the scheduler,
+    including statistics for each of the suspended threads.
+*/
+pub fn stats(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    let stats = lua.scheduler_stats();
+
+    let threads = stats
+        .threads
+        .into_iter()
+        .map(|thread| {
+            TableBuilder::new(lua.clone())?
+                .with_value("thread", thread.thread)?
+                .with_value("state", thread.state.as_str())?
+                .with_value("resumes", thread.resumes)?
+                .with_value("cpuTime", thread.cpu_time.as_secs_f64())?
+                .with_value("blockedOn", thread.blocked_on)?
+                .build()
+        })
+        .collect::<LuaResult<Vec<_>>>()?;
+
+    TableBuilder::new(lua.clone())?
+        .with_value("status", status_name(stats.status))?
+        .with_value("running", stats.running)?
+        .with_value("spawned", stats.spawned)?
+        .with_value("deferred", stats.deferred)?
+        .with_value("waiting", stats.waiting)?
+        .with_value("futures", stats.futures)?
+        .with_value("threads", lua.create_sequence_from(threads)?)?
+        .build()
+}
+
+/**
+    Returns a human-readable dump of the state of the scheduler,
+    and the stack traces of suspended threads.
+*/
+pub fn dump(lua: &Lua, (): ()) -> LuaResult<String> {
+    let stats = lua.scheduler_stats();
+
+    let mut dump = format!(
+        "Scheduler is {} - {} running, {} spawned, {} deferred, {} waiting, {} pending futures",
+        status_name(stats.status),
+        stats.running,
+        stats.spawned,
+        stats.deferred,
+        stats.waiting,
+        stats.futures,
+    );
+    for (index, thread) in stats.threads.iter().enumerate() {
+        write!(
+            dump,
+            "\n\nThread {} - {}, resumed {} {}, ran for {:.3}ms",
+            index + 1,
+            thread.state.as_str(),
+            thread.resumes,
+            if thread.resumes == 1 { "time" } else { "times" },
+            thread.cpu_time.as_secs_f64() * 1000.0,
+        )
+        .into_lua_err()?;
+        if let Some(reason) = &thread.blocked_on {
+            write!(dump, ", waiting for {reason}").into_lua_err()?;
+        }
+        for line in thread.traceback(lua)?.lines() {
+            write!(dump, "\n    {line}").into_lua_err()?;
+        }
+    }
+
+    Ok(dump)
+}
+
+fn status_name(status: Status) -> &'static str {
+    match status {
+        Status::NotStarted => "not started",
+        Status::Running => "running",
+        Status::Completed => "completed",
+    }
+}
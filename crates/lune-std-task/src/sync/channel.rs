@@ -0,0 +1,128 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, MultiEvent};
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    queue: VecDeque<LuaValue>,
+    capacity: Option<usize>,
+    closed: bool,
+}
+
+/**
+    A channel for sending values between Lua threads, optionally bounded.
+
+    Receiving yields until a value is available, and sending to a
+    bounded channel yields until there is space for another value.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Channel {
+    state: Rc<RefCell<ChannelState>>,
+    changed: MultiEvent,
+}
+
+impl Channel {
+    pub fn new(_: &Lua, capacity: Option<usize>) -> LuaResult<Self> {
+        if capacity == Some(0) {
+            return Err(LuaError::runtime(
+                "Invalid channel capacity - must be at least 1",
+            ));
+        }
+        Ok(Self {
+            state: Rc::new(RefCell::new(ChannelState {
+                capacity,
+                ..Default::default()
+            })),
+            changed: MultiEvent::new(),
+        })
+    }
+
+    /**
+        Tries to send a value without waiting, returning the value back if the channel is full.
+    */
+    fn try_send(&self, value: LuaValue) -> LuaResult<Result<(), LuaValue>> {
+        // NOTE: Receiving returns nil once the channel is closed, so
+        // sending nil would make the two impossible to tell apart
+        if value.is_nil() {
+            return Err(LuaError::runtime("Cannot send nil through a channel"));
+        }
+        let mut state = self.state.borrow_mut();
+        if state.closed {
+            return Err(LuaError::runtime("Cannot send to a closed channel"));
+        }
+        if state
+            .capacity
+            .is_some_and(|capacity| state.queue.len() >= capacity)
+        {
+            return Ok(Err(value));
+        }
+        state.queue.push_back(value);
+        drop(state);
+        self.changed.notify();
+        Ok(Ok(()))
+    }
+
+    /**
+        Tries to receive a value without waiting, returning `None` if the channel is empty.
+    */
+    fn try_recv(&self) -> Option<LuaValue> {
+        let value = self.state.borrow_mut().queue.pop_front();
+        if value.is_some() {
+            self.changed.notify();
+        }
+        value
+    }
+
+    async fn send(&self, mut value: LuaValue) -> LuaResult<()> {
+        loop {
+            let listener = self.changed.listen();
+            match self.try_send(value)? {
+                Ok(()) => return Ok(()),
+                Err(returned) => value = returned,
+            }
+            listener.await;
+        }
+    }
+
+    async fn recv(&self) -> LuaValue {
+        loop {
+            let listener = self.changed.listen();
+            if let Some(value) = self.try_recv() {
+                return value;
+            }
+            if self.state.borrow().closed {
+                return LuaValue::Nil;
+            }
+            listener.await;
+        }
+    }
+
+    fn close(&self) {
+        self.state.borrow_mut().closed = true;
+        self.changed.notify();
+    }
+}
+
+impl LuaUserData for Channel {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("send", |lua, this, value: LuaValue| {
+            let this = this.clone();
+            async move { lua.blocked_on("space in a channel", this.send(value)).await }
+        });
+        methods.add_async_method("recv", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { Ok(lua.blocked_on("a value from a channel", this.recv()).await) }
+        });
+        methods.add_method("trySend", |_, this, value: LuaValue| {
+            Ok(this.try_send(value)?.is_ok())
+        });
+        methods.add_method("tryRecv", |_, this, (): ()| Ok(this.try_recv()));
+        methods.add_method("close", |_, this, (): ()| {
+            this.close();
+            Ok(())
+        });
+        methods.add_method("isClosed", |_, this, (): ()| Ok(this.state.borrow().closed));
+        methods.add_method("len", |_, this, (): ()| Ok(this.state.borrow().queue.len()));
+    }
+}
@@ -0,0 +1,52 @@
+use std::{cell::RefCell, rc::Rc};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, OnceEvent};
+
+/**
+    A one-shot event that Lua threads may wait for, carrying the values it was set with.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Event {
+    values: Rc<RefCell<Option<LuaMultiValue>>>,
+    event: OnceEvent,
+}
+
+impl Event {
+    pub fn new(_: &Lua, (): ()) -> LuaResult<Self> {
+        // NOTE: A default OnceEvent is already notified, so we must use new here
+        Ok(Self {
+            values: Rc::new(RefCell::new(None)),
+            event: OnceEvent::new(),
+        })
+    }
+
+    fn set(&self, values: LuaMultiValue) -> LuaResult<()> {
+        let mut current = self.values.borrow_mut();
+        if current.is_some() {
+            return Err(LuaError::runtime("Event has already been set"));
+        }
+        current.replace(values);
+        drop(current);
+        self.event.notify();
+        Ok(())
+    }
+
+    async fn wait(&self) -> LuaMultiValue {
+        self.event.listen().await;
+        self.values.borrow().clone().unwrap_or_default()
+    }
+}
+
+impl LuaUserData for Event {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("wait", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { Ok(lua.blocked_on("an event to be set", this.wait()).await) }
+        });
+        methods.add_method("set", |_, this, values: LuaMultiValue| this.set(values));
+        methods.add_method("isSet", |_, this, (): ()| {
+            Ok(this.values.borrow().is_some())
+        });
+    }
+}
@@ -0,0 +1,9 @@
+mod channel;
+mod event;
+mod mutex;
+mod semaphore;
+
+pub(crate) use self::channel::Channel;
+pub(crate) use self::event::Event;
+pub(crate) use self::mutex::Mutex;
+pub(crate) use self::semaphore::Semaphore;
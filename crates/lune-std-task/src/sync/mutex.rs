@@ -0,0 +1,60 @@
+use std::{cell::Cell, rc::Rc};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, MultiEvent};
+
+/**
+    A mutual exclusion lock for Lua threads.
+
+    Locking yields until the mutex has been unlocked by whichever thread currently holds it.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Mutex {
+    locked: Rc<Cell<bool>>,
+    unlocked: MultiEvent,
+}
+
+impl Mutex {
+    pub fn new(_: &Lua, (): ()) -> LuaResult<Self> {
+        Ok(Self::default())
+    }
+
+    fn try_lock(&self) -> bool {
+        !self.locked.replace(true)
+    }
+
+    async fn lock(&self) {
+        loop {
+            let listener = self.unlocked.listen();
+            if self.try_lock() {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    fn unlock(&self) -> LuaResult<()> {
+        if !self.locked.replace(false) {
+            return Err(LuaError::runtime(
+                "Cannot unlock a mutex that is not locked",
+            ));
+        }
+        self.unlocked.notify();
+        Ok(())
+    }
+}
+
+impl LuaUserData for Mutex {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("lock", |lua, this, (): ()| {
+            let this = this.clone();
+            async move {
+                lua.blocked_on("a mutex to unlock", this.lock()).await;
+                Ok(())
+            }
+        });
+        methods.add_method("tryLock", |_, this, (): ()| Ok(this.try_lock()));
+        methods.add_method("unlock", |_, this, (): ()| this.unlock());
+        methods.add_method("isLocked", |_, this, (): ()| Ok(this.locked.get()));
+    }
+}
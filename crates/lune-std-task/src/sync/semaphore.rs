@@ -0,0 +1,74 @@
+use std::{cell::Cell, rc::Rc};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, MultiEvent};
+
+/**
+    A semaphore for limiting how many Lua threads may do something at the same time.
+
+    Acquiring permits yields until enough permits have been released by other threads.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Semaphore {
+    permits: Rc<Cell<usize>>,
+    released: MultiEvent,
+}
+
+impl Semaphore {
+    pub fn new(_: &Lua, permits: usize) -> LuaResult<Self> {
+        Ok(Self {
+            permits: Rc::new(Cell::new(permits)),
+            released: MultiEvent::new(),
+        })
+    }
+
+    fn try_acquire(&self, count: usize) -> bool {
+        let permits = self.permits.get();
+        if permits >= count {
+            self.permits.set(permits - count);
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn acquire(&self, count: usize) {
+        loop {
+            let listener = self.released.listen();
+            if self.try_acquire(count) {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    fn release(&self, count: usize) {
+        self.permits.set(self.permits.get().saturating_add(count));
+        self.released.notify();
+    }
+}
+
+impl LuaUserData for Semaphore {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("acquire", |lua, this, count: Option<usize>| {
+            let this = this.clone();
+            async move {
+                let count = count.unwrap_or(1);
+                let reason = match count {
+                    1 => String::from("a permit from a semaphore"),
+                    n => format!("{n} permits from a semaphore"),
+                };
+                lua.blocked_on(reason, this.acquire(count)).await;
+                Ok(())
+            }
+        });
+        methods.add_method("tryAcquire", |_, this, count: Option<usize>| {
+            Ok(this.try_acquire(count.unwrap_or(1)))
+        });
+        methods.add_method("release", |_, this, count: Option<usize>| {
+            this.release(count.unwrap_or(1));
+            Ok(())
+        });
+        methods.add_method("available", |_, this, (): ()| Ok(this.permits.get()));
+    }
+}
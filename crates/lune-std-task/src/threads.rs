@@ -0,0 +1,423 @@
+use futures_util::stream::{FuturesUnordered, StreamExt};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt, ThreadId};
+
+use lune_utils::TableBuilder;
+
+/**
+    The result of a thread, either its values, or the original value of its error.
+*/
+type ThreadResult = Result<LuaMultiValue, LuaValue>;
+
+/**
+    Results of threads spawned using the task library, letting
+    them be awaited, even after they have already completed.
+
+    Functions are spawned in threads that run them using a shared runner, which stores
+    the values they return, or the original values of the errors they raise, by itself.
+    Nothing else happens for threads that are never awaited, and the results of
+    threads are only waited for once something actually awaits them.
+
+    Results are stored in tables with weak keys, meaning they are
+    removed once the thread they belong to has been garbage collected.
+
+    Errors of threads are reported as usual, unless the thread is being awaited
+    when it errors, since the error is then raised in the awaiting thread instead.
+*/
+#[derive(Clone)]
+pub(crate) struct ThreadResults {
+    // NOTE: Threads are stored with a value of false until they complete,
+    // true if they completed without any values, or their packed values
+    results: LuaTable,
+    errors: LuaTable,
+    runner: LuaFunction,
+    raising: LuaFunction,
+}
+
+impl ThreadResults {
+    /**
+        Gets the thread results for the given Lua state, creating them if necessary.
+    */
+    pub fn get(lua: &Lua) -> LuaResult<Self> {
+        if let Some(this) = lua.app_data_ref::<Self>() {
+            return Ok(this.clone());
+        }
+
+        let meta = TableBuilder::new(lua.clone())?
+            .with_value("__mode", "k")?
+            .build_readonly()?;
+        let results = lua.create_table()?;
+        results.set_metatable(Some(meta.clone()))?;
+        let errors = lua.create_table()?;
+        errors.set_metatable(Some(meta))?;
+
+        let handler = {
+            let errors = errors.clone();
+            lua.create_function(move |lua, value: LuaValue| handle_error(lua, &errors, value))?
+        };
+        let coroutine = lua.globals().get::<LuaTable>("coroutine")?;
+        let table = lua.globals().get::<LuaTable>("table")?;
+        let env = TableBuilder::new(lua.clone())?
+            .with_value("xpcall", lua.globals().get::<LuaFunction>("xpcall")?)?
+            .with_value("error", lua.globals().get::<LuaFunction>("error")?)?
+            .with_value("select", lua.globals().get::<LuaFunction>("select")?)?
+            .with_value("running", coroutine.get::<LuaFunction>("running")?)?
+            .with_value("pack", table.get::<LuaFunction>("pack")?)?
+            .with_value("handler", handler)?
+            .with_value("results", results.clone())?
+            .build_readonly()?;
+        let (runner, raising) = lua
+            .load(WRAP_IMPL_LUA)
+            .set_name("=__task_wrap")
+            .set_environment(env)
+            .call::<(LuaFunction, LuaFunction)>(())?;
+
+        let this = Self {
+            results,
+            errors,
+            runner,
+            raising,
+        };
+        lua.set_app_data(this.clone());
+        Ok(this)
+    }
+
+    /**
+        Creates a thread for a function that is about to be spawned, which stores
+        the result of the function once it completes, so that it may be awaited.
+
+        The function is added to the front of the given arguments, and
+        those arguments must be used when spawning the created thread.
+    */
+    pub fn create_thread(
+        &self,
+        lua: &Lua,
+        function: LuaFunction,
+        args: &mut LuaMultiValue,
+    ) -> LuaResult<LuaThread> {
+        let thread = lua.create_thread(self.runner.clone())?;
+        self.results.raw_set(&thread, false)?;
+        args.push_front(LuaValue::Function(function));
+        Ok(thread)
+    }
+
+    /**
+        Starts tracking the result of the given thread, if it is not already being tracked.
+
+        Must be called before the thread is resumed for the first time, and
+        returns `true` if the thread was not already being tracked before.
+
+        This is only necessary for threads that were not created using
+        [`ThreadResults::create_thread`], which track their results by themselves.
+    */
+    pub fn track(&self, lua: &Lua, thread: &LuaThread) -> LuaResult<bool> {
+        if thread.status() != LuaThreadStatus::Resumable
+            || !self.results.raw_get::<LuaValue>(thread)?.is_nil()
+        {
+            return Ok(false);
+        }
+
+        lua.track_thread(ThreadId::from(thread));
+        self.results.raw_set(thread, false)?;
+        Ok(true)
+    }
+
+    /**
+        Watches a thread that was previously tracked using [`ThreadResults::track`],
+        storing its result once it has completed, or right away if it already has.
+
+        Watching happens in the background, and does not keep the scheduler running.
+    */
+    pub fn watch(&self, lua: &Lua, thread: LuaThread) -> LuaResult<()> {
+        let id = ThreadId::from(&thread);
+        if thread.status() != LuaThreadStatus::Resumable {
+            if let Some(result) = lua.get_thread_result(id) {
+                self.store(lua, &thread, result)?;
+            }
+            return Ok(());
+        }
+
+        let this = self.clone();
+        let inner_lua = lua.clone();
+        lua.spawn_local_background(async move {
+            inner_lua.wait_for_thread(id).await;
+            if let Some(result) = inner_lua.get_thread_result(id)
+                && let Err(e) = this.store(&inner_lua, &thread, result)
+            {
+                inner_lua.report_error(&LuaError::runtime(format!(
+                    "Failed to store thread result - {e}"
+                )));
+            }
+        });
+        Ok(())
+    }
+
+    /**
+        Stores the result of a thread, as given by the scheduler, unless
+        the thread has already stored its original result by itself.
+    */
+    fn store(
+        &self,
+        lua: &Lua,
+        thread: &LuaThread,
+        result: LuaResult<LuaMultiValue>,
+    ) -> LuaResult<()> {
+        if self.load(thread)?.is_some() {
+            return Ok(());
+        }
+        match result {
+            Ok(values) if values.is_empty() => self.results.raw_set(thread, true),
+            Ok(values) => {
+                let packed = lua.create_table_with_capacity(values.len(), 1)?;
+                packed.raw_set("n", values.len())?;
+                for (index, value) in values.into_iter().enumerate() {
+                    packed.raw_set(index + 1, value)?;
+                }
+                self.results.raw_set(thread, packed)
+            }
+            Err(e) => self.errors.raw_set(thread, LuaValue::Error(Box::new(e))),
+        }
+    }
+
+    fn load(&self, thread: &LuaThread) -> LuaResult<Option<ThreadResult>> {
+        let error = self.errors.raw_get::<LuaValue>(thread)?;
+        if !error.is_nil() {
+            return Ok(Some(Err(error)));
+        }
+        match self.results.raw_get::<LuaValue>(thread)? {
+            LuaValue::Boolean(true) => Ok(Some(Ok(LuaMultiValue::new()))),
+            LuaValue::Table(packed) => {
+                let n = packed.raw_get::<usize>("n")?;
+                let values = (1..=n)
+                    .map(|index| packed.raw_get::<LuaValue>(index))
+                    .collect::<LuaResult<LuaMultiValue>>()?;
+                Ok(Some(Ok(values)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /**
+        Wraps a function that returns packed thread results, as created by
+        [`pack_result`], so that it raises the original values of errors.
+    */
+    pub fn raising(&self, function: LuaFunction) -> LuaResult<LuaFunction> {
+        self.raising.call(function)
+    }
+
+    /**
+        Waits for the given thread to complete, and returns its result.
+
+        Only threads that were spawned using the task library may be waited for,
+        and any error of the thread while waiting is handled, and not reported.
+    */
+    pub async fn wait(&self, lua: &Lua, thread: &LuaThread) -> LuaResult<ThreadResult> {
+        if let Some(result) = self.load(thread)? {
+            return Ok(result);
+        }
+        if thread.status() == LuaThreadStatus::Running {
+            return Err(LuaError::runtime(
+                "Cannot await the currently running thread",
+            ));
+        }
+        if self.results.raw_get::<LuaValue>(thread)?.is_nil() {
+            return Err(LuaError::runtime(
+                "Cannot await a thread that was not spawned using the task library",
+            ));
+        }
+
+        // NOTE: The scheduler stores results of threads that are cancelled, or
+        // that never complete, as errors, so we wait for those using the scheduler
+        if thread.status() == LuaThreadStatus::Resumable {
+            let id = ThreadId::from(thread);
+            lua.track_thread(id);
+            lua.handling_thread_errors(id, lua.wait_for_thread(id))
+                .await;
+            if let Some(result) = lua.get_thread_result(id) {
+                self.store(lua, thread, result)?;
+            }
+        }
+
+        // NOTE: Threads that completed without storing a result were cancelled
+        // before anything started waiting for them, so we never got an error
+        Ok(self.load(thread)?.unwrap_or_else(|| {
+            Err(LuaValue::Error(Box::new(LuaError::runtime(
+                "Thread was cancelled",
+            ))))
+        }))
+    }
+}
+
+/**
+    Waits for a single thread to complete, returning its values or raising its error.
+*/
+pub async fn await_thread(lua: Lua, thread: LuaThread) -> LuaResult<LuaMultiValue> {
+    let results = ThreadResults::get(&lua)?;
+    let result = lua
+        .blocked_on("a thread to complete", results.wait(&lua, &thread))
+        .await?;
+    Ok(pack_result(result))
+}
+
+/**
+    Waits for all of the given threads to complete, returning a list of the values for each
+    thread, in order. Raises the error of the first thread that errors, without waiting further.
+*/
+pub async fn await_all(lua: Lua, threads: Vec<LuaThread>) -> LuaResult<LuaMultiValue> {
+    let results = ThreadResults::get(&lua)?;
+    let mut waiting = wait_many(&lua, &results, &threads);
+
+    let values = lua.create_table_with_capacity(threads.len(), 0)?;
+    let reason = "all of the awaited threads to complete";
+    while let Some((index, result)) = lua.blocked_on(reason, waiting.next()).await {
+        match result? {
+            Ok(result) => values.raw_set(index + 1, lua.create_sequence_from(result)?)?,
+            Err(e) => return Ok(pack_result(Err(e))),
+        }
+    }
+    Ok(pack_result(Ok(LuaMultiValue::from_iter([
+        LuaValue::Table(values),
+    ]))))
+}
+
+/**
+    Waits for the first of the given threads to complete successfully, returning
+    its index and values. Raises an error only if all of the threads error.
+*/
+pub async fn await_any(lua: Lua, threads: Vec<LuaThread>) -> LuaResult<LuaMultiValue> {
+    if threads.is_empty() {
+        return Err(LuaError::runtime("Expected at least one thread"));
+    }
+
+    let results = ThreadResults::get(&lua)?;
+    let mut waiting = wait_many(&lua, &results, &threads);
+
+    let mut last_error = None;
+    let reason = "any of the awaited threads to complete";
+    while let Some((index, result)) = lua.blocked_on(reason, waiting.next()).await {
+        match result? {
+            Ok(values) => return Ok(pack_result(Ok(with_index(index, values)?))),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    let last_error = last_error.expect("at least one thread was awaited");
+    Err(LuaError::runtime(format!(
+        "All threads errored, the last error was:\n{}",
+        last_error.to_string()?
+    )))
+}
+
+/**
+    Waits for the first of the given threads to complete, returning its index
+    and values, or raising its error, if the first thread to complete errored.
+*/
+pub async fn await_race(lua: Lua, threads: Vec<LuaThread>) -> LuaResult<LuaMultiValue> {
+    if threads.is_empty() {
+        return Err(LuaError::runtime("Expected at least one thread"));
+    }
+
+    let results = ThreadResults::get(&lua)?;
+    let mut waiting = wait_many(&lua, &results, &threads);
+
+    let (index, result) = lua
+        .blocked_on(
+            "the first of the awaited threads to complete",
+            waiting.next(),
+        )
+        .await
+        .expect("at least one thread was awaited");
+    match result? {
+        Ok(values) => Ok(pack_result(Ok(with_index(index, values)?))),
+        Err(e) => Ok(pack_result(Err(e))),
+    }
+}
+
+fn wait_many<'a>(
+    lua: &'a Lua,
+    results: &'a ThreadResults,
+    threads: &'a [LuaThread],
+) -> FuturesUnordered<impl Future<Output = (usize, LuaResult<ThreadResult>)> + 'a> {
+    threads
+        .iter()
+        .enumerate()
+        .map(|(index, thread)| async move { (index, results.wait(lua, thread).await) })
+        .collect()
+}
+
+fn with_index(index: usize, values: LuaMultiValue) -> LuaResult<LuaMultiValue> {
+    let mut values = values;
+    values.push_front(LuaValue::Integer(i64::try_from(index + 1).into_lua_err()?));
+    Ok(values)
+}
+
+/**
+    Packs the result of a thread for a function wrapped using [`ThreadResults::raising`],
+    which raises the original error value, since errors returned from Rust can only
+    ever be raised as userdata, and not as the value that the thread errored with.
+*/
+fn pack_result(result: ThreadResult) -> LuaMultiValue {
+    match result {
+        Ok(mut values) => {
+            values.push_front(LuaValue::Boolean(true));
+            values
+        }
+        Err(value) => LuaMultiValue::from_iter([LuaValue::Boolean(false), value]),
+    }
+}
+
+/**
+    Handles an error raised in a spawned function, storing its original value and
+    returning it as an error with a traceback - same as if it was not protected.
+*/
+fn handle_error(lua: &Lua, errors: &LuaTable, value: LuaValue) -> LuaResult<LuaValue> {
+    if let LuaValue::Error(_) = value {
+        // NOTE: Errors from Rust are userdata that keep their own
+        // traceback, and are not modified when raised again in Lua
+        errors.raw_set(lua.current_thread(), &value)?;
+        return Ok(value);
+    }
+
+    let message = value.to_string()?;
+    let traceback = lua.traceback(Some(&message), 1)?.to_string_lossy();
+
+    // NOTE: The last two levels of the traceback are always the protected
+    // call and the runner calling it, which should not be shown to users
+    let traceback = traceback.rsplitn(3, '\n').last().unwrap_or_default();
+    let error = LuaValue::Error(Box::new(LuaError::RuntimeError(traceback.to_string())));
+
+    // NOTE: Storing nil would be the same as not storing anything, so
+    // for nil errors we store the error with the traceback instead
+    let original = if value.is_nil() { &error } else { &value };
+    errors.raw_set(lua.current_thread(), original)?;
+    Ok(error)
+}
+
+const WRAP_IMPL_LUA: &str = r"
+local function raise(ok, ...)
+    if ok then
+        return ...
+    end
+    error((...), 0)
+end
+
+local function finish(ok, ...)
+    if ok then
+        results[running()] = if select('#', ...) == 0 then true else pack(...)
+    end
+    return raise(ok, ...)
+end
+
+local function run(f, ...)
+    return finish(xpcall(f, handler, ...))
+end
+
+local function raising(f)
+    return function(...)
+        return raise(f(...))
+    end
+end
+
+return run, raising
+";
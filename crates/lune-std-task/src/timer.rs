@@ -0,0 +1,228 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use futures_lite::prelude::*;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Context, LuaSchedulerExt, LuaSpawnExt, MultiEvent};
+
+// NOTE: This is shared between all Luau VMs in the process,
+// so that clock values may be compared between workers too
+static CLOCK_START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/**
+    Returns the number of seconds that have passed since an arbitrary point in time.
+
+    Unlike `os.time`, this is monotonic, and never goes backwards.
+
+    When using virtual time, this instead returns the amount of virtual time that has passed.
+*/
+pub fn clock(lua: &Lua, (): ()) -> LuaResult<f64> {
+    let clock = lua.scheduler_clock();
+    let elapsed = if clock.is_virtual() {
+        clock.elapsed()
+    } else {
+        CLOCK_START.elapsed()
+    };
+    Ok(elapsed.as_secs_f64())
+}
+
+struct TimerInner {
+    period: Duration,
+    repeating: bool,
+    callback: LuaFunction,
+    args: LuaMultiValue,
+    // The context of the thread that created the timer, inherited by each run of the function
+    context: Context,
+    running: Cell<bool>,
+    // Incremented whenever the timer is stopped or reset, so
+    // that any previous timer loop knows that it should stop
+    generation: Cell<u64>,
+    changed: MultiEvent,
+}
+
+impl TimerInner {
+    fn run(&self, lua: &Lua) -> LuaResult<()> {
+        let thread = lua.create_thread(self.callback.clone())?;
+        lua.set_thread_context(&thread, self.context.clone())?;
+        lua.push_thread_front(thread, self.args.clone())?;
+        Ok(())
+    }
+}
+
+/**
+    A timer that calls a function in a new thread once its duration has passed,
+    either once, or repeatedly, until stopped. Created using `task.timer` and `task.interval`.
+
+    Repeating timers are scheduled relative to when they were started, and not relative
+    to when the function last ran, so that they do not drift over time. Any intervals
+    that were missed entirely, for example because another thread did not yield for
+    a long time, are skipped instead of running the function many times in a row.
+*/
+#[derive(Clone)]
+pub(crate) struct Timer {
+    inner: Rc<TimerInner>,
+}
+
+impl Timer {
+    fn create(
+        lua: &Lua,
+        secs: f64,
+        callback: LuaFunction,
+        args: LuaMultiValue,
+        repeating: bool,
+    ) -> LuaResult<Self> {
+        let period = Duration::try_from_secs_f64(secs)
+            .ok()
+            .filter(|period| !repeating || !period.is_zero())
+            .ok_or_else(|| {
+                LuaError::runtime(if repeating {
+                    format!("Invalid interval - expected a positive number, got {secs}")
+                } else {
+                    format!("Invalid duration - expected a non-negative number, got {secs}")
+                })
+            })?;
+
+        let timer = Self {
+            inner: Rc::new(TimerInner {
+                period,
+                repeating,
+                callback,
+                args,
+                context: lua.current_context(),
+                running: Cell::new(false),
+                generation: Cell::new(0),
+                changed: MultiEvent::new(),
+            }),
+        };
+        timer.start(lua);
+        Ok(timer)
+    }
+
+    pub fn new_once(
+        lua: &Lua,
+        (secs, callback, args): (f64, LuaFunction, LuaMultiValue),
+    ) -> LuaResult<Self> {
+        Self::create(lua, secs, callback, args, false)
+    }
+
+    pub fn new_interval(
+        lua: &Lua,
+        (secs, callback, args): (f64, LuaFunction, LuaMultiValue),
+    ) -> LuaResult<Self> {
+        Self::create(lua, secs, callback, args, true)
+    }
+
+    /**
+        Starts the timer from the current point in time, stopping any previous timer loop.
+    */
+    fn start(&self, lua: &Lua) {
+        let generation = self.invalidate();
+        self.inner.running.set(true);
+
+        let inner = Rc::clone(&self.inner);
+        let inner_lua = lua.clone();
+        lua.spawn_local(async move {
+            let clock = inner_lua.scheduler_clock();
+            let mut deadline = clock.now() + inner.period;
+            loop {
+                let changed = inner.changed.listen();
+                let fired = async {
+                    clock.sleep_until(deadline).await;
+                    true
+                };
+                let changed = async {
+                    changed.await;
+                    false
+                };
+                let fired = fired.or(changed).await;
+                if inner.generation.get() != generation {
+                    return;
+                }
+                if !fired {
+                    continue;
+                }
+
+                if let Err(e) = inner.run(&inner_lua) {
+                    inner_lua.report_error(&LuaError::runtime(format!(
+                        "Failed to run timer function - {e}"
+                    )));
+                }
+
+                if !inner.repeating {
+                    inner.running.set(false);
+                    return;
+                }
+                deadline = next_deadline(deadline, inner.period, clock.now());
+            }
+        });
+    }
+
+    fn stop(&self) {
+        self.invalidate();
+        self.inner.running.set(false);
+    }
+
+    fn invalidate(&self) -> u64 {
+        let generation = self.inner.generation.get() + 1;
+        self.inner.generation.set(generation);
+        self.inner.changed.notify();
+        generation
+    }
+}
+
+impl LuaUserData for Timer {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("stop", |_, this, (): ()| {
+            this.stop();
+            Ok(())
+        });
+        methods.add_method("reset", |lua, this, (): ()| {
+            this.start(lua);
+            Ok(())
+        });
+        methods.add_method("isRunning", |_, this, (): ()| Ok(this.inner.running.get()));
+    }
+}
+
+/**
+    Returns the next deadline for a repeating timer that fired at the given deadline,
+    skipping over any deadlines that have already passed, to stay on the same schedule.
+*/
+fn next_deadline(deadline: Instant, period: Duration, now: Instant) -> Instant {
+    let next = deadline + period;
+    if next > now {
+        return next;
+    }
+    let behind = now.duration_since(deadline).as_nanos();
+    let missed = behind / period.as_nanos();
+    let missed = u32::try_from(missed).unwrap_or(u32::MAX);
+    deadline + period.saturating_mul(missed.saturating_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_deadline_on_schedule() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let next = next_deadline(start, period, start + Duration::from_millis(30));
+        assert_eq!(next, start + period);
+    }
+
+    #[test]
+    fn next_deadline_skips_missed() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let next = next_deadline(start, period, start + Duration::from_millis(350));
+        assert_eq!(next, start + period * 4);
+        let next = next_deadline(start, period, start + Duration::from_millis(300));
+        assert_eq!(next, start + period * 4);
+    }
+}
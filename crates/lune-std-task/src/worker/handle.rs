@@ -0,0 +1,187 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use async_channel::{Receiver, Sender};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSpawnExt, OnceEvent};
+
+use super::{WorkerOutcome, WorkerPort, value::WorkerValue};
+
+/**
+    A handle to a worker, used by its parent to communicate with it, and to manage its lifecycle.
+*/
+pub(crate) struct Worker {
+    sender: Sender<WorkerValue>,
+    receiver: Receiver<WorkerValue>,
+    terminated: Arc<AtomicBool>,
+    terminate: Sender<()>,
+    outcome: Rc<RefCell<Option<WorkerOutcome>>>,
+    exited: OnceEvent,
+}
+
+impl Worker {
+    pub fn new(
+        lua: &Lua,
+        sender: Sender<WorkerValue>,
+        receiver: Receiver<WorkerValue>,
+        terminated: Arc<AtomicBool>,
+        terminate: Sender<()>,
+        outcome_receiver: Receiver<WorkerOutcome>,
+    ) -> Self {
+        let outcome = Rc::new(RefCell::new(None));
+        // NOTE: Must not use OnceEvent::default here, since that is already notified
+        let exited = OnceEvent::new();
+
+        // Wait for the worker to exit in the background, so that workers which are never
+        // joined or terminated do not keep the parent running - if the parent exits first,
+        // this is dropped along with its scheduler, and the guard terminates the worker
+        let mut guard = TerminateGuard {
+            sender: sender.clone(),
+            terminated: Arc::clone(&terminated),
+            terminate: terminate.clone(),
+            exited: false,
+        };
+        let inner_outcome = Rc::clone(&outcome);
+        let inner_exited = exited.clone();
+        lua.spawn_local_background(async move {
+            let result = outcome_receiver
+                .recv()
+                .await
+                .unwrap_or_else(|_| WorkerOutcome::Errored("Worker exited unexpectedly".into()));
+            guard.exited();
+            inner_outcome.borrow_mut().replace(result);
+            inner_exited.notify();
+        });
+
+        Self {
+            sender,
+            receiver,
+            terminated,
+            terminate,
+            outcome,
+            exited,
+        }
+    }
+
+    fn send(&self, value: LuaValue) -> LuaResult<()> {
+        let value = WorkerValue::from_lua(value)?;
+        self.sender
+            .try_send(value)
+            .map_err(|_| LuaError::runtime("Cannot send to a worker that has exited"))
+    }
+
+    async fn recv(&self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self.receiver.recv().await {
+            Ok(value) => value.into_lua(lua),
+            Err(_) => Ok(LuaValue::Nil),
+        }
+    }
+
+    async fn join(&self, lua: &Lua) -> LuaResult<LuaMultiValue> {
+        self.exited.listen().await;
+        let outcome = self.outcome.borrow().clone();
+        match outcome.expect("outcome is set before exit is notified") {
+            WorkerOutcome::Completed(values) => values
+                .into_iter()
+                .map(|value| value.into_lua(lua))
+                .collect(),
+            WorkerOutcome::Errored(message) => Err(LuaError::runtime(message)),
+            WorkerOutcome::Terminated => Err(LuaError::runtime("Worker was terminated")),
+        }
+    }
+
+    fn terminate(&self) {
+        if self.outcome.borrow().is_none() {
+            self.terminated.store(true, Ordering::SeqCst);
+            self.terminate.close();
+        }
+        self.sender.close();
+    }
+
+    fn is_running(&self) -> bool {
+        self.outcome.borrow().is_none()
+    }
+}
+
+impl LuaUserData for Worker {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("send", |_, this, value: LuaValue| this.send(value));
+        methods.add_async_method(
+            "recv",
+            |lua, this, (): ()| async move { this.recv(&lua).await },
+        );
+        methods.add_async_method(
+            "join",
+            |lua, this, (): ()| async move { this.join(&lua).await },
+        );
+        methods.add_method("terminate", |_, this, (): ()| {
+            this.terminate();
+            Ok(())
+        });
+        methods.add_method("isRunning", |_, this, (): ()| Ok(this.is_running()));
+    }
+}
+
+/**
+    Terminates a worker and closes its channels once dropped, unless it has already exited.
+*/
+struct TerminateGuard {
+    sender: Sender<WorkerValue>,
+    terminated: Arc<AtomicBool>,
+    terminate: Sender<()>,
+    exited: bool,
+}
+
+impl TerminateGuard {
+    fn exited(&mut self) {
+        self.exited = true;
+    }
+}
+
+impl Drop for TerminateGuard {
+    fn drop(&mut self) {
+        if !self.exited {
+            self.terminated.store(true, Ordering::SeqCst);
+            self.terminate.close();
+            self.sender.close();
+        }
+    }
+}
+
+/**
+    The parent of a worker, used by the worker to communicate with it.
+*/
+pub(crate) struct WorkerParent {
+    port: WorkerPort,
+}
+
+impl WorkerParent {
+    pub fn new(port: WorkerPort) -> Self {
+        Self { port }
+    }
+}
+
+impl LuaUserData for WorkerParent {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("send", |_, this, value: LuaValue| {
+            let value = WorkerValue::from_lua(value)?;
+            this.port
+                .sender
+                .try_send(value)
+                .map_err(|_| LuaError::runtime("Cannot send to a parent that has exited"))
+        });
+        methods.add_async_method("recv", |lua, this, (): ()| async move {
+            match this.port.receiver.recv().await {
+                Ok(value) => value.into_lua(&lua),
+                Err(_) => Ok(LuaValue::Nil),
+            }
+        });
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use async_channel::{Receiver, Sender};
+use futures_lite::prelude::*;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
+
+use lune_utils::path::{clean_path, constants::FILE_CHUNK_PREFIX};
+
+mod handle;
+mod value;
+
+use self::value::WorkerValue;
+
+pub(crate) use self::handle::{Worker, WorkerParent};
+
+/**
+    The final outcome of a worker, sent back to its parent once the worker exits.
+*/
+#[derive(Debug, Clone)]
+pub(crate) enum WorkerOutcome {
+    Completed(Vec<WorkerValue>),
+    Errored(String),
+    Terminated,
+}
+
+/**
+    The end of a worker that communicates with its parent, stored in the app data of the worker.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct WorkerPort {
+    sender: Sender<WorkerValue>,
+    receiver: Receiver<WorkerValue>,
+}
+
+/**
+    Spawns workers for `task.worker`, each with their own Luau VM and scheduler.
+
+    The task library can not create fully featured runtimes by itself, so a spawner
+    must be provided by the runtime embedding it, and stored in the app data of the
+    Lua state before the task library is created. Spawning happens on a thread pool,
+    and the spawner should create a runtime for the worker and run it using
+    [`WorkerContext::run`], or report a failure using [`WorkerContext::fail`].
+*/
+#[derive(Clone)]
+pub struct WorkerSpawner {
+    spawn: Arc<dyn Fn(WorkerContext) + Send + Sync>,
+}
+
+impl WorkerSpawner {
+    /**
+        Creates a new worker spawner from the given function.
+    */
+    pub fn new<F>(spawn: F) -> Self
+    where
+        F: Fn(WorkerContext) + Send + Sync + 'static,
+    {
+        Self {
+            spawn: Arc::new(spawn),
+        }
+    }
+}
+
+/**
+    The context of a single worker, given to a [`WorkerSpawner`] when the worker is spawned.
+*/
+pub struct WorkerContext {
+    path: PathBuf,
+    port: WorkerPort,
+    terminated: Arc<AtomicBool>,
+    terminate: Receiver<()>,
+    outcome: Sender<WorkerOutcome>,
+}
+
+impl WorkerContext {
+    /**
+        Returns the path of the module that the worker should run.
+    */
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /**
+        Attaches the worker to the given Lua state, letting the task library
+        in it communicate with the parent, and letting the parent terminate it.
+
+        Must be called before the task library is created in the Lua state.
+
+        # Panics
+
+        Panics if the Lua state does not have a scheduler.
+    */
+    pub fn attach(&self, lua: &Lua) {
+        lua.set_app_data(self.port.clone());
+
+        // NOTE: Termination is also checked by the interrupt, so that
+        // workers running long computations without yielding may stop,
+        // and the scheduler is told to exit, since the yielded thread
+        // would otherwise be reported as never having been resumed
+        let terminated = Arc::clone(&self.terminated);
+        lua.set_scheduler_interrupt(move |lua| {
+            Ok(if terminated.load(Ordering::SeqCst) {
+                lua.set_exit_code(1);
+                LuaVmState::Yield
+            } else {
+                LuaVmState::Continue
+            })
+        });
+    }
+
+    /**
+        Runs the worker until the given future completes, or until the worker is
+        terminated, blocking the current thread. The values or the error that the
+        future resolves to are then sent back to the parent of the worker.
+    */
+    pub fn run<F, E>(self, fut: F)
+    where
+        F: Future<Output = Result<LuaMultiValue, E>>,
+        E: fmt::Display,
+    {
+        let terminate = self.terminate.clone();
+        let result = async_io::block_on(async { Some(fut.await) }.or(async {
+            terminate.recv().await.ok();
+            None
+        }));
+
+        // NOTE: Terminating a worker while it is running may also make
+        // it complete early, which should still count as being terminated
+        let result = result.filter(|_| !self.terminated.load(Ordering::SeqCst));
+        let outcome = match result {
+            Some(Ok(values)) => match values.into_iter().map(WorkerValue::from_lua).collect() {
+                Ok(values) => WorkerOutcome::Completed(values),
+                Err(e) => WorkerOutcome::Errored(e.to_string()),
+            },
+            Some(Err(e)) => WorkerOutcome::Errored(e.to_string()),
+            None => WorkerOutcome::Terminated,
+        };
+        self.finish(outcome);
+    }
+
+    /**
+        Reports that the worker could not be started, with the given error.
+    */
+    pub fn fail(self, error: impl fmt::Display) {
+        self.finish(WorkerOutcome::Errored(error.to_string()));
+    }
+
+    fn finish(self, outcome: WorkerOutcome) {
+        // NOTE: The Lua state of the worker may hold on to its port for longer
+        // than expected, so we close it explicitly, letting the parent know that
+        // no more messages will arrive, while still receiving any buffered ones
+        self.port.sender.close();
+        self.port.receiver.close();
+        self.outcome.try_send(outcome).ok();
+    }
+}
+
+/**
+    Creates a new worker running the module at the given path, relative to the calling script.
+*/
+pub(crate) fn create(lua: &Lua, path: String) -> LuaResult<Worker> {
+    let Some(spawner) = lua.app_data_ref::<WorkerSpawner>().map(|s| s.clone()) else {
+        return Err(LuaError::runtime(
+            "Workers are not supported by the current runtime",
+        ));
+    };
+
+    // Resolve the path relative to the calling script, if it is relative
+    let mut resolved = PathBuf::from(&path);
+    if path.starts_with("./") || path.starts_with("../") {
+        let caller = lua
+            .inspect_stack(1, |debug| {
+                let source = debug.source().source?;
+                let file = source.strip_prefix(FILE_CHUNK_PREFIX)?;
+                Some(Path::new(file).parent()?.to_path_buf())
+            })
+            .flatten();
+        if let Some(dir) = caller {
+            resolved = clean_path(dir.join(&path));
+        }
+    }
+
+    let (parent_sender, worker_receiver) = async_channel::unbounded();
+    let (worker_sender, parent_receiver) = async_channel::unbounded();
+    let (terminate_sender, terminate_receiver) = async_channel::bounded(1);
+    let (outcome_sender, outcome_receiver) = async_channel::bounded(1);
+    let terminated = Arc::new(AtomicBool::new(false));
+
+    let context = WorkerContext {
+        path: resolved,
+        port: WorkerPort {
+            sender: worker_sender,
+            receiver: worker_receiver,
+        },
+        terminated: Arc::clone(&terminated),
+        terminate: terminate_receiver,
+        outcome: outcome_sender,
+    };
+    blocking::unblock(move || (spawner.spawn)(context)).detach();
+
+    Ok(Worker::new(
+        lua,
+        parent_sender,
+        parent_receiver,
+        terminated,
+        terminate_sender,
+        outcome_receiver,
+    ))
+}
+
+/**
+    Gets the port for communicating with the parent, if the given Lua state is a worker.
+*/
+pub(crate) fn parent(lua: &Lua) -> Option<WorkerParent> {
+    lua.app_data_ref::<WorkerPort>()
+        .map(|port| WorkerParent::new(port.clone()))
+}
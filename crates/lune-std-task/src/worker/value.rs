@@ -0,0 +1,81 @@
+use mlua::prelude::*;
+
+/**
+    A value that may be sent between workers, which each have their own Luau VM.
+
+    Only plain data may be sent - tables are copied deeply, and
+    functions, threads, and userdata can not be sent at all.
+*/
+#[derive(Debug, Clone)]
+pub(crate) enum WorkerValue {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    Vector(f32, f32, f32),
+    String(Vec<u8>),
+    Buffer(Vec<u8>),
+    Table(Vec<(WorkerValue, WorkerValue)>),
+}
+
+impl WorkerValue {
+    pub fn from_lua(value: LuaValue) -> LuaResult<Self> {
+        Self::from_lua_inner(value, &mut Vec::new())
+    }
+
+    fn from_lua_inner(value: LuaValue, seen: &mut Vec<*const std::ffi::c_void>) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::Nil,
+            LuaValue::Boolean(b) => Self::Boolean(b),
+            LuaValue::Integer(i) => Self::Integer(i),
+            LuaValue::Number(n) => Self::Number(n),
+            LuaValue::Vector(v) => Self::Vector(v.x(), v.y(), v.z()),
+            LuaValue::String(s) => Self::String(s.as_bytes().to_vec()),
+            LuaValue::Buffer(b) => Self::Buffer(b.to_vec()),
+            LuaValue::Table(t) => {
+                let pointer = t.to_pointer();
+                if seen.contains(&pointer) {
+                    return Err(LuaError::runtime(
+                        "Cannot send tables with cyclic references to or from workers",
+                    ));
+                }
+                seen.push(pointer);
+                let mut pairs = Vec::new();
+                for pair in t.pairs::<LuaValue, LuaValue>() {
+                    let (key, value) = pair?;
+                    pairs.push((
+                        Self::from_lua_inner(key, seen)?,
+                        Self::from_lua_inner(value, seen)?,
+                    ));
+                }
+                seen.pop();
+                Self::Table(pairs)
+            }
+            value => {
+                return Err(LuaError::runtime(format!(
+                    "Cannot send values of type '{}' to or from workers",
+                    value.type_name()
+                )));
+            }
+        })
+    }
+
+    pub fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        Ok(match self {
+            Self::Nil => LuaValue::Nil,
+            Self::Boolean(b) => LuaValue::Boolean(b),
+            Self::Integer(i) => LuaValue::Integer(i),
+            Self::Number(n) => LuaValue::Number(n),
+            Self::Vector(x, y, z) => LuaValue::Vector(LuaVector::new(x, y, z)),
+            Self::String(s) => LuaValue::String(lua.create_string(s)?),
+            Self::Buffer(b) => LuaValue::Buffer(lua.create_buffer(b)?),
+            Self::Table(pairs) => {
+                let table = lua.create_table_with_capacity(0, pairs.len())?;
+                for (key, value) in pairs {
+                    table.raw_set(key.into_lua(lua)?, value.into_lua(lua)?)?;
+                }
+                LuaValue::Table(table)
+            }
+        })
+    }
+}
@@ -11,6 +11,12 @@ pub use self::global::LuneStandardGlobal;
 pub use self::globals::version::set_global_version;
 pub use self::library::LuneStandardLibrary;
 
+#[cfg(feature = "process")]
+pub use lune_std_process::listen_for_signals;
+
+#[cfg(feature = "task")]
+pub use lune_std_task::{WorkerContext, WorkerSpawner};
+
 /**
     Injects all standard globals into the given Lua state / VM.
 
@@ -1,4 +1,4 @@
-use std::{env, io::stdin, process::ExitCode};
+use std::{env, io::stdin, process::ExitCode, time::Duration};
 
 use anyhow::{Context, Result};
 use blocking::Unblock;
@@ -25,10 +25,21 @@ impl RunCommand {
             .ok()
             .is_some_and(|s| matches!(s.as_str(), "0" | "false" | "off"));
 
+        // Check if the user wants to be warned about threads that do not yield
+        let watchdog = env::var("LUNE_WATCHDOG")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .filter(|timeout| !timeout.is_zero());
+
         // Create a new lune runtime with all globals & run the script
         let mut rt = Runtime::new()?
             .with_args(self.script_args)
-            .with_jit(!jit_disabled);
+            .with_jit(!jit_disabled)
+            .with_signal_handling(true);
+        if let Some(timeout) = watchdog {
+            rt = rt.with_watchdog(timeout);
+        }
 
         // Figure out if we should run stdin or run a file,
         // reading from stdin is marked by passing a single "-"
@@ -7,10 +7,12 @@ use std::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
+    time::Duration,
 };
 
 use async_fs as fs;
 use lune_utils::{
+    fmt::Label,
     path::{LuauModulePath, constants::FILE_CHUNK_PREFIX},
     process::{ProcessArgs, ProcessEnv, ProcessJitEnablement},
 };
@@ -32,6 +34,7 @@ pub struct RuntimeReturnValues {
     pub errored: bool,
     /// The final values returned by the main thread.
     pub values: LuaMultiValue,
+    error: Option<LuaError>,
 }
 
 impl RuntimeReturnValues {
@@ -58,6 +61,14 @@ impl RuntimeReturnValues {
     pub fn success(&self) -> bool {
         self.status() == 0
     }
+
+    /**
+        Returns the error thrown by the main thread, if any.
+    */
+    #[must_use]
+    pub fn error(&self) -> Option<&LuaError> {
+        self.error.as_ref()
+    }
 }
 
 /**
@@ -69,6 +80,8 @@ pub struct Runtime {
     args: ProcessArgs,
     env: ProcessEnv,
     jit: ProcessJitEnablement,
+    #[cfg_attr(not(feature = "std-process"), allow(dead_code))]
+    signals: bool,
 }
 
 impl Runtime {
@@ -144,6 +157,7 @@ impl Runtime {
             args,
             env,
             jit,
+            signals: false,
         })
     }
 
@@ -190,6 +204,75 @@ impl Runtime {
         self
     }
 
+    /**
+        Enables or disables virtual time.
+
+        When virtual time is enabled, functions in the `task` library that wait for time to pass,
+        such as `task.wait`, complete instantly, in order, as soon as no other threads are able
+        to run, and time may also be advanced manually using `task.advance`. This is useful
+        for testing code that waits or has timeouts, quickly and deterministically.
+
+        Workers spawned using `task.worker` do not inherit virtual time.
+    */
+    #[must_use]
+    pub fn with_virtual_time(self, enabled: bool) -> Self {
+        self.sched.set_virtual_time(enabled);
+        self
+    }
+
+    /**
+        Sets the maximum number of queued Lua threads to resume during each tick of the scheduler.
+
+        Any other queued threads are resumed during later ticks, letting timers and other work
+        make progress in between, which prevents long bursts of threads, such as when a server
+        is flooded with messages, from starving everything else. Threads spawned with a higher
+        priority using `task.spawn` or `task.defer` are always resumed first.
+
+        By default, there is no limit, and all queued threads are resumed during each tick.
+
+        Workers spawned using `task.worker` do not inherit the resume budget.
+
+        # Panics
+
+        Panics if the given budget is zero.
+    */
+    #[must_use]
+    pub fn with_resume_budget(self, budget: usize) -> Self {
+        self.sched.set_resume_budget(budget);
+        self
+    }
+
+    /**
+        Enables a watchdog, which prints a warning with a stack traceback to stderr
+        whenever a Lua thread runs for longer than the given timeout without yielding.
+
+        Workers spawned using `task.worker` do not inherit the watchdog.
+    */
+    #[must_use]
+    pub fn with_watchdog(self, timeout: Duration) -> Self {
+        self.sched.set_watchdog(timeout, |report| {
+            eprintln!("{} {report}", Label::Warn);
+        });
+        self
+    }
+
+    /**
+        Enables graceful handling of signals that would otherwise terminate the process,
+        such as `SIGINT` and `SIGTERM`, stopping the scheduler and returning the exit code
+        for the signal once one is received, instead of being terminated immediately.
+
+        Scripts may still handle these signals themselves using `process.onSignal`.
+
+        Signals are received by the whole process, so this should only be enabled
+        for the main runtime of a process - it is disabled by default, and workers
+        spawned using `task.worker` do not inherit it.
+    */
+    #[must_use]
+    pub fn with_signal_handling(mut self, enabled: bool) -> Self {
+        self.signals = enabled;
+        self
+    }
+
     /**
         Adds a custom library to the runtime, making it available through `require`.
 
@@ -350,6 +433,13 @@ impl Runtime {
         self.lua.set_app_data(self.env.clone());
         self.lua.set_app_data(self.jit);
 
+        // Let scripts spawn workers, which each run in their own runtime
+        #[cfg(feature = "std-task")]
+        {
+            let spawner = create_worker_spawner(&self.args, &self.env, self.jit);
+            self.lua.set_app_data(spawner);
+        }
+
         // Inject all the standard libraries that are enabled - this needs to be done after
         // storing the args/env, since some standard libraries use those during initialization
         #[cfg(any(
@@ -371,6 +461,16 @@ impl Runtime {
         // Enable / disable the JIT as requested, before loading anything
         self.lua.enable_jit(self.jit.enabled());
 
+        // Listen for signals that would otherwise terminate the process, which
+        // must happen within the running scheduler, before the main thread runs
+        #[cfg(feature = "std-process")]
+        if self.signals {
+            let listen = self
+                .lua
+                .create_function(|lua, (): ()| lune_std::listen_for_signals(lua))?;
+            self.sched.push_thread_front(listen, ())?;
+        }
+
         // Load our "main" thread
         let main = self
             .lua
@@ -381,20 +481,65 @@ impl Runtime {
         let main_thread_id = self.sched.push_thread_back(main, ())?;
         self.sched.run().await;
 
-        let main_thread_values = self
+        let (main_thread_values, main_thread_error) = match self
             .sched
             .get_thread_result(main_thread_id)
             .unwrap_or_else(|| Ok(LuaMultiValue::new())) // Ignore missing result (interruption), we just want to extract values
-            .unwrap_or_default(); // Ignore any errors from the script, we just want to extract values
+        {
+            Ok(values) => (values, None),
+            Err(e) => (LuaMultiValue::new(), Some(e)),
+        };
 
         Ok(RuntimeReturnValues {
             code: self.sched.get_exit_code(),
             errored: got_any_error.load(Ordering::SeqCst),
             values: main_thread_values,
+            error: main_thread_error,
         })
     }
 }
 
+/**
+    Creates a worker spawner for `task.worker`, which runs each
+    worker in a new runtime, using the given args, env, and jit.
+*/
+#[cfg(feature = "std-task")]
+fn create_worker_spawner(
+    args: &ProcessArgs,
+    env: &ProcessEnv,
+    jit: ProcessJitEnablement,
+) -> lune_std::WorkerSpawner {
+    // NOTE: Workers get copies of the args and env, so that
+    // changes made in one runtime do not affect the others
+    let args = args.all();
+    let env = env.get_all();
+    lune_std::WorkerSpawner::new(move |worker| {
+        let mut rt = match Runtime::new() {
+            Ok(rt) => rt
+                .with_args(args.clone())
+                .with_env(env.clone())
+                .with_jit(jit),
+            Err(e) => return worker.fail(RuntimeError::from(e)),
+        };
+        worker.attach(&rt.lua);
+
+        let path = worker.path().to_path_buf();
+        worker.run(async move {
+            let result = rt.run_file(path).await?;
+            if let Some(e) = result.error {
+                return Err(RuntimeError::from(e));
+            }
+            if !result.success() {
+                return Err(RuntimeError::from(LuaError::runtime(format!(
+                    "Worker exited with status {}",
+                    result.status()
+                ))));
+            }
+            Ok(result.values)
+        });
+    })
+}
+
 fn strip_shebang(mut contents: Vec<u8>) -> Vec<u8> {
     if contents.starts_with(b"#!")
         && let Some(first_newline_idx) = contents
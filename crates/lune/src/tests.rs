@@ -1,6 +1,7 @@
 use std::env::set_current_dir;
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::sync::{PoisonError, RwLock};
 
 use anyhow::Result;
 use console::set_colors_enabled;
@@ -12,7 +13,30 @@ use crate::Runtime;
 
 const ARGS: &[&str] = &["Foo", "Bar"];
 
+/*
+    Tests run in parallel within the same process, and share its working directory.
+
+    Most tests only need the working directory to stay the same while they run, but
+    some tests change it, and must then run exclusively, without any other tests.
+*/
+static CWD_LOCK: RwLock<()> = RwLock::new(());
+
 fn run_test(path: &str) -> Result<ExitCode> {
+    let _guard = CWD_LOCK.read().unwrap_or_else(PoisonError::into_inner);
+    run_test_inner(path, false)
+}
+
+fn run_test_exclusive(path: &str) -> Result<ExitCode> {
+    let _guard = CWD_LOCK.write().unwrap_or_else(PoisonError::into_inner);
+    run_test_inner(path, false)
+}
+
+fn run_test_virtual_time(path: &str) -> Result<ExitCode> {
+    let _guard = CWD_LOCK.read().unwrap_or_else(PoisonError::into_inner);
+    run_test_inner(path, true)
+}
+
+fn run_test_inner(path: &str, virtual_time: bool) -> Result<ExitCode> {
     async_io::block_on(async {
         // We need to change the current directory to the workspace root since
         // we are in a sub-crate and tests would run relative to the sub-crate
@@ -26,7 +50,10 @@ fn run_test(path: &str) -> Result<ExitCode> {
         set_colors_enabled_stderr(false);
 
         // The rest of the test logic can continue as normal
-        let mut rt = Runtime::new()?.with_args(ARGS).with_jit(true);
+        let mut rt = Runtime::new()?
+            .with_args(ARGS)
+            .with_jit(true)
+            .with_virtual_time(virtual_time);
 
         let script_path = workspace_dir.join("tests").join(format!("{path}.luau"));
         let script_values = rt.run_file(script_path).await?;
@@ -44,6 +71,24 @@ macro_rules! create_tests {
     )* }
 }
 
+macro_rules! create_exclusive_tests {
+    ($($name:ident: $value:expr,)*) => { $(
+        #[test]
+        fn $name() -> Result<ExitCode> {
+        	run_test_exclusive($value)
+        }
+    )* }
+}
+
+macro_rules! create_virtual_time_tests {
+    ($($name:ident: $value:expr,)*) => { $(
+        #[test]
+        fn $name() -> Result<ExitCode> {
+        	run_test_virtual_time($value)
+        }
+    )* }
+}
+
 #[cfg(any(
     feature = "std-datetime",
     feature = "std-fs",
@@ -124,8 +169,12 @@ create_tests! {
     net_request_redirect: "net/request/redirect",
 
     net_serve_addresses: "net/serve/addresses",
+    net_serve_context: "net/serve/context",
+    net_serve_cookies: "net/serve/cookies",
+    net_serve_forms: "net/serve/forms",
     net_serve_handles: "net/serve/handles",
     net_serve_non_blocking: "net/serve/non_blocking",
+    net_serve_proxy: "net/serve/proxy",
     net_serve_requests: "net/serve/requests",
     net_serve_websockets: "net/serve/websockets",
 
@@ -139,6 +188,8 @@ create_tests! {
 
     net_url_encode: "net/url/encode",
     net_url_decode: "net/url/decode",
+    net_url_parse: "net/url/parse",
+    net_url_build: "net/url/build",
 }
 
 #[cfg(feature = "std-process")]
@@ -147,16 +198,32 @@ create_tests! {
     process_cwd: "process/cwd",
     process_env: "process/env",
     process_exit: "process/exit",
+    process_info: "process/info",
     process_exec_async: "process/exec/async",
     process_exec_basic: "process/exec/basic",
     process_exec_cwd: "process/exec/cwd",
+    process_exec_isolation: "process/exec/isolation",
     process_exec_no_panic: "process/exec/no_panic",
     process_exec_shell: "process/exec/shell",
     process_exec_stdin: "process/exec/stdin",
     process_exec_stdio: "process/exec/stdio",
+    process_exec_timeout: "process/exec/timeout",
+    process_pipeline_basic: "process/pipeline/basic",
+    process_pipeline_redirect: "process/pipeline/redirect",
+    process_pool: "process/pool",
+    process_spawn_detached: "process/create/detached",
+    process_spawn_lines: "process/create/lines",
     process_spawn_non_blocking: "process/create/non_blocking",
+    process_spawn_pty: "process/create/pty",
+    process_spawn_signal: "process/create/signal",
     process_spawn_status: "process/create/status",
     process_spawn_stream: "process/create/stream",
+    process_which: "process/which",
+}
+
+#[cfg(feature = "std-process")]
+create_exclusive_tests! {
+    process_chdir: "process/chdir",
 }
 
 #[cfg(feature = "std-regex")]
@@ -274,9 +341,30 @@ create_tests! {
 
 #[cfg(feature = "std-task")]
 create_tests! {
+    task_await: "task/await",
     task_cancel: "task/cancel",
+    task_channel: "task/channel",
+    task_clock: "task/clock",
+    task_context: "task/context",
+    task_deadlock: "task/deadlock",
     task_defer: "task/defer",
     task_delay: "task/delay",
+    task_event: "task/event",
+    task_mutex: "task/mutex",
+    task_priority: "task/priority",
+    task_scope: "task/scope",
+    task_semaphore: "task/semaphore",
     task_spawn: "task/spawn",
+    task_stats: "task/stats",
+    task_timeout: "task/timeout",
     task_wait: "task/wait",
+    task_worker: "task/worker",
+    task_worker_unjoined: "task/worker_unjoined",
+}
+
+#[cfg(feature = "std-task")]
+create_virtual_time_tests! {
+    task_interval: "task/interval",
+    task_timer: "task/timer",
+    task_virtual_time: "task/virtual_time",
 }
@@ -0,0 +1,95 @@
+#![cfg(unix)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::io::{BufRead, BufReader};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/*
+    Signals are received by the whole process, so these tests run scripts using
+    the lune binary in separate processes, instead of in the test process itself.
+*/
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn spawn_script(path: &str, args: &[&str]) -> Child {
+    let script = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/").to_string() + path;
+    Command::new(env!("CARGO_BIN_EXE_lune"))
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../../"))
+        .arg("run")
+        .arg(script)
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn lune")
+}
+
+fn wait_until_ready(child: &mut Child) {
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut line = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut line)
+        .expect("failed to read from lune");
+    assert_eq!(line.trim(), "ready", "script did not become ready");
+}
+
+fn send_signal(child: &Child, signal: &str) {
+    let status = Command::new("kill")
+        .arg(format!("-{signal}"))
+        .arg(child.id().to_string())
+        .status()
+        .expect("failed to run kill");
+    assert!(status.success(), "failed to send {signal} to lune");
+}
+
+fn wait_with_timeout(child: &mut Child) -> ExitStatus {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().expect("failed to wait for lune") {
+            return status;
+        }
+        if start.elapsed() > TIMEOUT {
+            let _ = child.kill();
+            panic!("lune did not exit after being signalled");
+        }
+        sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn signal_handlers() {
+    let mut child = spawn_script("process/signal/handler.luau", &[]);
+    let status = wait_with_timeout(&mut child);
+    assert!(status.success(), "signal handler script failed: {status}");
+}
+
+#[test]
+fn signal_default_exits_gracefully() {
+    for (signal, code) in [("TERM", 143), ("INT", 130), ("HUP", 129)] {
+        let mut child = spawn_script("process/signal/default.luau", &[]);
+        wait_until_ready(&mut child);
+        send_signal(&child, signal);
+
+        let status = wait_with_timeout(&mut child);
+        assert_eq!(status.signal(), None, "lune was terminated by SIG{signal}");
+        assert_eq!(status.code(), Some(code), "lune exited with the wrong code");
+    }
+}
+
+#[test]
+fn signal_default_repeated_terminates() {
+    let mut child = spawn_script("process/signal/default.luau", &["busy"]);
+    wait_until_ready(&mut child);
+    send_signal(&child, "INT");
+    sleep(Duration::from_millis(250));
+    assert!(
+        child.try_wait().expect("failed to wait for lune").is_none(),
+        "lune should not exit while its scheduler is blocked"
+    );
+    send_signal(&child, "INT");
+
+    let status = wait_with_timeout(&mut child);
+    assert_eq!(status.code(), Some(130), "lune exited with the wrong code");
+}
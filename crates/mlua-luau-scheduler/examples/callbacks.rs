@@ -2,8 +2,10 @@
 #![allow(clippy::missing_panics_doc)]
 #![allow(clippy::cargo_common_metadata)]
 
+use std::sync::{Arc, Mutex};
+
 use mlua::prelude::*;
-use mlua_luau_scheduler::Scheduler;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, Scheduler, ThreadId};
 
 use async_io::block_on;
 
@@ -21,14 +23,44 @@ pub fn main() -> LuaResult<()> {
 
     // Create a new scheduler with custom callbacks
     let sched = Scheduler::new(lua.clone());
-    sched.set_error_callback(|e| {
-        println!(
-            "Captured error from Lua!\n{}\n{e}\n{}",
-            "-".repeat(15),
-            "-".repeat(15)
-        );
+    let fns = Functions::new(lua.clone())?;
+    let events = Arc::new(Mutex::new(Vec::new()));
+    sched.set_error_callback({
+        let events = Arc::clone(&events);
+        move |e| {
+            println!(
+                "Captured error from Lua!\n{}\n{e}\n{}",
+                "-".repeat(15),
+                "-".repeat(15)
+            );
+            events.lock().unwrap().push(format!("error: {e}"));
+        }
     });
 
+    lua.globals().set("spawn", fns.spawn)?;
+    lua.globals().set("defer", fns.defer)?;
+    lua.globals().set("log", {
+        let events = Arc::clone(&events);
+        lua.create_function(move |_, message: String| {
+            events.lock().unwrap().push(message);
+            Ok(())
+        })?
+    })?;
+
+    // Errors of threads are not reported while something is waiting for them,
+    // since the waiting thread handles them instead, here by raising them
+    lua.globals().set(
+        "await",
+        lua.create_async_function(|lua, thread: LuaThread| async move {
+            let id = ThreadId::from(&thread);
+            lua.track_thread(id);
+            lua.handling_thread_errors(id, lua.wait_for_thread(id))
+                .await;
+            lua.get_thread_result(id)
+                .unwrap_or_else(|| Err(LuaError::runtime("Thread has no result")))
+        })?,
+    )?;
+
     // Load the main script into the scheduler, and keep track of the thread we spawn
     let main = lua.load(MAIN_SCRIPT);
     let id = sched.push_thread_front(main, ())?;
@@ -39,6 +71,14 @@ pub fn main() -> LuaResult<()> {
     // We should have gotten the error back from our script
     assert!(sched.get_thread_result(id).unwrap().is_err());
 
+    // Errors should have been reported right away, unless they were handled
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 4, "unexpected events: {events:?}");
+    assert!(events[0].starts_with("error: ") && events[0].contains("Reported right away"));
+    assert_eq!(events[1], "after spawn");
+    assert_eq!(events[2], "handled");
+    assert!(events[3].starts_with("error: ") && events[3].contains("Oh no!"));
+
     Ok(())
 }
 
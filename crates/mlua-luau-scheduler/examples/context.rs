@@ -0,0 +1,80 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{cell::RefCell, rc::Rc};
+
+use async_io::block_on;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, Scheduler};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/context.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment
+    let lua = Lua::new();
+    let sched = Scheduler::new(lua.clone());
+    let fns = Functions::new(lua.clone())?;
+
+    lua.globals().set("spawn", fns.spawn)?;
+    lua.globals().set("defer", fns.defer)?;
+    lua.globals().set(
+        "setContext",
+        lua.create_function(|lua, (key, value): (String, LuaValue)| {
+            let context = lua.current_context().with(lua, &key, value)?;
+            lua.set_thread_context(&lua.current_thread(), context)
+        })?,
+    )?;
+
+    // Native functions can read the context of the thread that called them,
+    // here we use it to prefix all logged messages with the current request ID
+    let logged = Rc::new(RefCell::new(Vec::new()));
+    lua.globals().set("log", {
+        let logged = Rc::clone(&logged);
+        lua.create_function(move |lua, message: String| {
+            let context = lua.current_context();
+            let line = match context.get::<String>("requestId")? {
+                Some(id) => format!("[{id}] {message}"),
+                None => message,
+            };
+            println!("{line}");
+            logged.borrow_mut().push(line);
+            Ok(())
+        })?
+    })?;
+
+    // Load the main script into the scheduler
+    let main = lua.load(MAIN_SCRIPT);
+    sched.push_thread_front(main, ())?;
+
+    // Run until completion
+    block_on(sched.run());
+
+    // Every message should have been logged with the request that it belongs to
+    let mut logged = logged.take();
+    logged.sort();
+    assert_eq!(
+        logged,
+        vec![
+            "[request-1] handling request",
+            "[request-1] handling request in background",
+            "[request-2] handling request",
+            "[request-2] handling request in background",
+            "no request",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_context() -> LuaResult<()> {
+    main()
+}
@@ -0,0 +1,96 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_io::{Timer, block_on};
+use futures_lite::future::yield_now;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, MultiEvent, Scheduler};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/deadlock.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment, with a signal that
+    // threads can wait for, and that only other threads can send
+    let lua = Lua::new();
+    let signal = MultiEvent::new();
+    let signal_wait = signal.clone();
+    lua.globals().set(
+        "waitForSignal",
+        lua.create_async_function(move |lua, ()| {
+            let listener = signal_wait.listen();
+            async move {
+                lua.blocked_on("a signal", listener).await;
+                Ok(())
+            }
+        })?,
+    )?;
+    lua.globals().set(
+        "sendSignal",
+        lua.create_function(move |_, ()| {
+            signal.notify();
+            Ok(())
+        })?,
+    )?;
+    lua.globals().set(
+        "sleep",
+        lua.create_async_function(|_, duration: f64| async move {
+            yield_now().await;
+            Timer::after(Duration::from_secs_f64(duration)).await;
+            Ok(())
+        })?,
+    )?;
+
+    // Capture any errors, which should include the deadlock report
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_inner = Arc::clone(&errors);
+    let sched = Scheduler::new(lua.clone());
+    sched.set_error_callback(move |e| {
+        println!("Captured error from Lua!\n{e}");
+        errors_inner.lock().unwrap().push(e.to_string());
+    });
+
+    // Load the main script into the scheduler
+    let fns = Functions::new(lua.clone())?;
+    lua.globals().set("spawn", fns.spawn)?;
+
+    let main = lua.load(MAIN_SCRIPT);
+    let id = sched.push_thread_front(main, ())?;
+
+    // Run until completion, which should stop early instead of waiting forever
+    block_on(sched.run());
+
+    // The deadlock should have been reported once, listing both threads
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1, "only the deadlock should be reported");
+    assert!(errors[0].contains("Deadlock detected"));
+    assert_eq!(errors[0].matches("is waiting for a signal").count(), 2);
+
+    // The main thread should also have the deadlock as its result
+    let result = sched.get_thread_result(id).unwrap();
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Deadlock detected")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_deadlock() -> LuaResult<()> {
+    main()
+}
@@ -0,0 +1,104 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{cell::Cell, rc::Rc};
+
+use async_io::block_on;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{
+    Functions, LuaSchedulerExt, LuaSpawnExt, MultiEvent, Priority, Scheduler,
+};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/priorities.luau");
+
+const BUDGET: usize = 10;
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment
+    let lua = Lua::new();
+    let sched = Scheduler::new(lua.clone());
+    let fns = Functions::new(lua.clone())?;
+
+    lua.globals().set("defer", fns.defer)?;
+    lua.globals().set(
+        "setPriority",
+        lua.create_function(|lua, (thread, priority): (LuaThread, String)| {
+            let priority = match priority.as_str() {
+                "low" => Priority::Low,
+                "high" => Priority::High,
+                _ => Priority::Normal,
+            };
+            lua.set_thread_priority(&thread, priority)
+        })?,
+    )?;
+
+    // Keep track of how many threads have run, and when some "housekeeping" ran
+    let processed = Rc::new(Cell::new(0));
+    let housekeeping = Rc::new(Cell::new(None));
+    lua.globals().set("process", {
+        let processed = Rc::clone(&processed);
+        lua.create_function(move |_, ()| {
+            processed.set(processed.get() + 1);
+            Ok(())
+        })?
+    })?;
+
+    // Housekeeping is a future that gets woken up by the very first thread that runs
+    let event = MultiEvent::new();
+    lua.globals().set("wakeHousekeeping", {
+        let event = event.clone();
+        lua.create_function(move |_, ()| {
+            event.notify();
+            Ok(())
+        })?
+    })?;
+    lua.globals().set("startHousekeeping", {
+        let processed = Rc::clone(&processed);
+        let housekeeping = Rc::clone(&housekeeping);
+        lua.create_function(move |lua, ()| {
+            let listener = event.listen();
+            let processed = Rc::clone(&processed);
+            let housekeeping = Rc::clone(&housekeeping);
+            lua.spawn_local(async move {
+                listener.await;
+                housekeeping.set(Some(processed.get()));
+            });
+            Ok(())
+        })?
+    })?;
+
+    // Load the main script into the scheduler, with a resume budget
+    sched.set_resume_budget(BUDGET);
+    let main = lua.load(MAIN_SCRIPT);
+    sched.push_thread_front(main, ())?;
+
+    // Run until completion
+    block_on(sched.run());
+
+    // Housekeeping should have run after the first batch of threads, not after all of them
+    let processed = processed.get();
+    let housekeeping = housekeeping.get().expect("housekeeping should have run");
+    println!("Housekeeping ran after {housekeeping} out of {processed} threads");
+    assert_eq!(processed, 1000);
+    assert!(housekeeping <= BUDGET);
+
+    // Threads should have run in order of priority
+    let order = lua.globals().get::<Vec<String>>("order")?;
+    assert_eq!(order.first().map(String::as_str), Some("high"));
+    assert_eq!(order.last().map(String::as_str), Some("low"));
+
+    Ok(())
+}
+
+#[test]
+fn test_priorities() -> LuaResult<()> {
+    main()
+}
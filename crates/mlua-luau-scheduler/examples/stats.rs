@@ -0,0 +1,81 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::time::Duration;
+
+use async_io::{Timer, block_on};
+use futures_lite::future::yield_now;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, Scheduler, Status, ThreadState};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/stats.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment
+    let lua = Lua::new();
+    lua.globals().set(
+        "sleep",
+        lua.create_async_function(|_, duration: f64| async move {
+            yield_now().await;
+            Timer::after(Duration::from_secs_f64(duration)).await;
+            Ok(())
+        })?,
+    )?;
+    lua.globals().set(
+        "stats",
+        lua.create_function(|lua, ()| {
+            let stats = lua.scheduler_stats();
+            assert_eq!(stats.status, Status::Running);
+            assert_eq!(stats.running, 1, "only the main thread should be running");
+
+            let waiting = stats
+                .threads
+                .iter()
+                .filter(|t| t.state == ThreadState::Waiting)
+                .collect::<Vec<_>>();
+            assert_eq!(waiting.len(), stats.waiting);
+            assert!(waiting.iter().all(|t| t.resumes >= 1));
+
+            Ok((stats.spawned, stats.deferred, stats.waiting))
+        })?,
+    )?;
+
+    // Load the main script into a scheduler
+    let sched = Scheduler::new(lua.clone());
+    let fns = Functions::new(lua.clone())?;
+    lua.globals().set("spawn", fns.spawn)?;
+    lua.globals().set("defer", fns.defer)?;
+
+    let main = lua.load(MAIN_SCRIPT);
+    sched.push_thread_front(main, ())?;
+
+    // Nothing should be waiting or running before the scheduler starts
+    let stats = sched.stats();
+    assert_eq!(stats.status, Status::NotStarted);
+    assert_eq!(stats.spawned, 1);
+    assert_eq!(stats.running + stats.waiting + stats.futures, 0);
+
+    // Run until completion
+    block_on(sched.run());
+
+    // Nothing should be left once the scheduler has completed
+    let stats = sched.stats();
+    assert_eq!(stats.status, Status::Completed);
+    assert_eq!(stats.spawned + stats.deferred + stats.waiting, 0);
+    assert!(stats.threads.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_stats() -> LuaResult<()> {
+    main()
+}
@@ -0,0 +1,61 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::time::{Duration, Instant};
+
+use async_io::block_on;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, Scheduler};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/virtual_time.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment, with a sleep function that uses the scheduler clock
+    let lua = Lua::new();
+    let sched = Scheduler::new(lua.clone());
+    let fns = Functions::new(lua.clone())?;
+
+    lua.globals().set("spawn", fns.spawn)?;
+    lua.globals().set(
+        "sleep",
+        lua.create_async_function(|lua, duration: f64| async move {
+            let clock = lua.scheduler_clock();
+            let before = clock.now();
+            clock.sleep(Duration::from_secs_f64(duration)).await;
+            Ok((clock.now() - before).as_secs_f64())
+        })?,
+    )?;
+    lua.globals().set(
+        "now",
+        lua.create_function(|lua, ()| Ok(lua.scheduler_clock().elapsed().as_secs_f64()))?,
+    )?;
+
+    // Load the main script into the scheduler, using virtual time
+    sched.set_virtual_time(true);
+    let main = lua.load(MAIN_SCRIPT);
+    let id = sched.push_thread_front(main, ())?;
+
+    // Run until completion, which should take (virtually) no real time at all
+    let before = Instant::now();
+    block_on(sched.run());
+    assert!(before.elapsed() < Duration::from_secs(1));
+
+    // Virtual time should have skipped ahead by exactly the longest sleep
+    assert_eq!(sched.clock().elapsed(), Duration::from_secs(90));
+    sched.get_thread_result(id).unwrap()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_virtual_time() -> LuaResult<()> {
+    main()
+}
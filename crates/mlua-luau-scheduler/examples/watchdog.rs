@@ -0,0 +1,76 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_io::block_on;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, Scheduler};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/watchdog.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment
+    let lua = Lua::new();
+
+    // Create a new scheduler with a watchdog, capturing its reports
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_inner = Arc::clone(&reports);
+    let sched = Scheduler::new(lua.clone());
+    sched.set_watchdog(Duration::from_millis(50), move |report| {
+        println!("Captured report from watchdog!\n{report}");
+        reports_inner.lock().unwrap().push(report.to_string());
+    });
+
+    // Set an interrupt of our own, which should be chained with the watchdog
+    let interrupts = Arc::new(AtomicUsize::new(0));
+    let interrupts_inner = Arc::clone(&interrupts);
+    lua.set_scheduler_interrupt(move |_| {
+        interrupts_inner.fetch_add(1, Ordering::SeqCst);
+        Ok(LuaVmState::Continue)
+    });
+
+    // Load the main script into the scheduler
+    let main = lua.load(MAIN_SCRIPT).set_name("=watchdog");
+    sched.push_thread_front(main, ())?;
+
+    // Run until completion
+    block_on(sched.run());
+
+    // Our interrupt should have been called while running, and
+    // should still be set once the watchdog has been removed
+    let during = interrupts.load(Ordering::SeqCst);
+    assert!(during > 0, "interrupt should be called while running");
+    lua.load("for _ = 1, 1_000_000 do end").exec()?;
+    assert!(
+        interrupts.load(Ordering::SeqCst) > during,
+        "interrupt should be restored after running"
+    );
+
+    // The spinning function should have been reported exactly once
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1, "thread should only be reported once");
+    assert!(reports[0].contains("without yielding"));
+    assert!(reports[0].contains("function spin"));
+
+    Ok(())
+}
+
+#[test]
+fn test_watchdog() -> LuaResult<()> {
+    main()
+}
@@ -0,0 +1,204 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use async_io::Timer;
+
+use crate::events::OnceEvent;
+
+type TimerKey = (Instant, u64);
+
+#[derive(Debug)]
+struct ClockInner {
+    is_virtual: Cell<bool>,
+    start: Instant,
+    elapsed: Cell<Duration>,
+    next_key: Cell<u64>,
+    // Pending virtual timers, in the order that they should fire
+    timers: RefCell<BTreeMap<TimerKey, OnceEvent>>,
+    // Pending manual advances of virtual time, in the order that they should complete
+    advances: RefCell<BTreeMap<TimerKey, OnceEvent>>,
+}
+
+/**
+    The clock used by a [`Scheduler`](crate::Scheduler) for sleeping and measuring time.
+
+    By default, this is the real system clock. When virtual time is enabled using
+    [`Scheduler::set_virtual_time`](crate::Scheduler::set_virtual_time), time instead
+    stands still while any Lua threads or futures can make progress, and skips ahead
+    to the next pending sleep as soon as none of them can - running all sleeps
+    instantly, and deterministically, in order.
+
+    Note that only sleeps using this clock are affected by virtual time.
+*/
+#[derive(Debug, Clone)]
+pub struct Clock {
+    inner: Rc<ClockInner>,
+}
+
+impl Clock {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Rc::new(ClockInner {
+                is_virtual: Cell::new(false),
+                start: Instant::now(),
+                elapsed: Cell::new(Duration::ZERO),
+                next_key: Cell::new(0),
+                timers: RefCell::new(BTreeMap::new()),
+                advances: RefCell::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    pub(crate) fn set_virtual(&self, is_virtual: bool) {
+        self.inner.is_virtual.set(is_virtual);
+    }
+
+    /**
+        Returns `true` if this clock uses virtual time.
+    */
+    #[must_use]
+    pub fn is_virtual(&self) -> bool {
+        self.inner.is_virtual.get()
+    }
+
+    /**
+        Returns the current time of this clock.
+
+        For virtual time, this only changes when the clock advances.
+    */
+    #[must_use]
+    pub fn now(&self) -> Instant {
+        if self.is_virtual() {
+            self.inner.start + self.inner.elapsed.get()
+        } else {
+            Instant::now()
+        }
+    }
+
+    /**
+        Returns the amount of time that has passed since this clock was created.
+    */
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.now().saturating_duration_since(self.inner.start)
+    }
+
+    /**
+        Sleeps until the given deadline has been reached.
+    */
+    pub async fn sleep_until(&self, deadline: Instant) {
+        if self.is_virtual() {
+            if deadline <= self.now() {
+                return;
+            }
+            let entry = self.insert(&self.inner.timers, deadline);
+            entry.event.listen().await;
+        } else {
+            Timer::at(deadline).await;
+        }
+    }
+
+    /**
+        Sleeps for the given duration.
+    */
+    pub async fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration).await;
+    }
+
+    /**
+        Advances virtual time by the given duration, firing any sleeps that finish
+        along the way, in order, and letting any Lua threads or futures that were
+        waiting for them make progress before firing the next.
+
+        The returned future completes once time has been advanced.
+
+        # Panics
+
+        Panics if this clock does not use virtual time.
+    */
+    pub async fn advance(&self, duration: Duration) {
+        assert!(
+            self.is_virtual(),
+            "time can only be advanced manually when using virtual time"
+        );
+        let entry = self.insert(&self.inner.advances, self.now() + duration);
+        entry.event.listen().await;
+    }
+
+    fn insert<'a>(
+        &'a self,
+        map: &'a RefCell<BTreeMap<TimerKey, OnceEvent>>,
+        deadline: Instant,
+    ) -> ClockEntry<'a> {
+        let key = (deadline, self.inner.next_key.get());
+        self.inner.next_key.set(key.1 + 1);
+
+        let event = OnceEvent::new();
+        map.borrow_mut().insert(key, event.clone());
+        ClockEntry { map, key, event }
+    }
+
+    /**
+        Advances virtual time to the next pending sleep or manual advance, and fires it.
+
+        Must only be called when no Lua threads or futures are able to make progress.
+
+        Returns `true` if time was advanced, and something may now make progress.
+    */
+    pub(crate) fn advance_idle(&self) -> bool {
+        if !self.is_virtual() {
+            return false;
+        }
+
+        let next_timer = self.inner.timers.borrow().keys().next().copied();
+        let next_advance = self.inner.advances.borrow().keys().next().copied();
+        let (map, key) = match (next_timer, next_advance) {
+            (Some(timer), Some(advance)) if advance.0 < timer.0 => (&self.inner.advances, advance),
+            (Some(timer), _) => (&self.inner.timers, timer),
+            (None, Some(advance)) => (&self.inner.advances, advance),
+            (None, None) => return false,
+        };
+
+        // NOTE: Pending deadlines should never be in the past, but
+        // we make extra sure that time never goes backwards here
+        let deadline = key.0.max(self.now());
+        self.inner.elapsed.set(deadline - self.inner.start);
+
+        // Fire everything that is due at this exact point in time, in order, which
+        // lets any threads waiting for the same deadline run before time moves on
+        let mut due = Vec::new();
+        {
+            let mut map = map.borrow_mut();
+            while let Some(entry) = map.first_entry() {
+                if entry.key().0 > key.0 {
+                    break;
+                }
+                due.push(entry.remove());
+            }
+        }
+        for event in due {
+            event.notify();
+        }
+
+        true
+    }
+}
+
+/**
+    A pending virtual sleep or advance, which is removed from its clock once dropped.
+*/
+struct ClockEntry<'a> {
+    map: &'a RefCell<BTreeMap<TimerKey, OnceEvent>>,
+    key: TimerKey,
+    event: OnceEvent,
+}
+
+impl Drop for ClockEntry<'_> {
+    fn drop(&mut self) {
+        self.map.borrow_mut().remove(&self.key);
+    }
+}
@@ -0,0 +1,133 @@
+use mlua::prelude::*;
+
+use crate::util::create_weak_keys_table;
+
+/**
+    Context-local values, associated with a Lua thread.
+
+    A context is immutable, and inherited by any threads that are spawned, deferred,
+    or resumed as coroutines from the thread it belongs to. Setting a value creates
+    a new context, which does not affect any threads that already inherited the
+    previous one, or the thread that a context was inherited from.
+
+    This makes contexts suitable for propagating values such as request
+    IDs for logging, without passing them to every function explicitly.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    // NOTE: Values are stored in a Lua table, which is never modified once created,
+    // so that contexts can be shared between threads cheaply, and so that any values
+    // stored in a context are managed by the Lua garbage collector like usual
+    values: Option<LuaTable>,
+}
+
+impl Context {
+    /**
+        Returns `true` if this context contains no values.
+    */
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_none()
+    }
+
+    /**
+        Returns the value for the given key, or `None` if it has not been set.
+
+        # Errors
+
+        Errors if the value could not be converted to the requested type.
+    */
+    pub fn get<V: FromLua>(&self, key: &str) -> LuaResult<Option<V>> {
+        match &self.values {
+            Some(values) => values.raw_get(key),
+            None => Ok(None),
+        }
+    }
+
+    /**
+        Returns all of the values in this context, sorted by their keys.
+
+        # Errors
+
+        Errors if any of the keys are not valid UTF-8.
+    */
+    pub fn entries(&self) -> LuaResult<Vec<(String, LuaValue)>> {
+        let Some(values) = &self.values else {
+            return Ok(Vec::new());
+        };
+        let mut entries = values
+            .pairs::<String, LuaValue>()
+            .collect::<LuaResult<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    /**
+        Creates a new context containing all of the values in this context,
+        and the given value set for the given key, or removed if it is `nil`.
+
+        # Errors
+
+        Errors if out of memory, or if the value could not be converted to Lua.
+    */
+    pub fn with(&self, lua: &Lua, key: &str, value: impl IntoLua) -> LuaResult<Self> {
+        let value = value.into_lua(lua)?;
+
+        let values = lua.create_table()?;
+        if let Some(current) = &self.values {
+            current.for_each(|k: LuaValue, v: LuaValue| values.raw_set(k, v))?;
+        }
+        values.raw_set(key, value)?;
+
+        Ok(Self {
+            values: (!values.is_empty()).then_some(values),
+        })
+    }
+}
+
+/**
+    Storage for the contexts of all threads in a scheduler.
+
+    Contexts are stored in a table with weak keys, so that the context
+    of a thread is removed as soon as the thread is garbage collected.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct ThreadContexts {
+    map: LuaTable,
+}
+
+impl ThreadContexts {
+    pub fn new(lua: &Lua) -> LuaResult<Self> {
+        let map = create_weak_keys_table(lua)?;
+        Ok(Self { map })
+    }
+
+    pub fn get(&self, thread: &LuaThread) -> Context {
+        Context {
+            values: self.map.raw_get(thread).ok().flatten(),
+        }
+    }
+
+    pub fn set(&self, thread: &LuaThread, context: Context) -> LuaResult<()> {
+        // NOTE: Empty contexts are stored as false, and not removed, so that
+        // threads which have cleared their context do not inherit a new one
+        match context.values {
+            Some(values) => self.map.raw_set(thread, values),
+            None => self.map.raw_set(thread, false),
+        }
+    }
+
+    /**
+        Makes the child thread inherit the context of the parent thread,
+        unless the child thread already has a context of its own.
+    */
+    pub fn inherit(&self, parent: &LuaThread, child: &LuaThread) -> LuaResult<()> {
+        if self.map.raw_get::<LuaValue>(child)?.is_nil() {
+            let context = self.get(parent);
+            if !context.is_empty() {
+                self.set(child, context)?;
+            }
+        }
+        Ok(())
+    }
+}
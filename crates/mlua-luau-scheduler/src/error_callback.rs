@@ -2,6 +2,8 @@ use std::{cell::RefCell, rc::Rc};
 
 use mlua::prelude::*;
 
+use crate::threads::{ThreadId, ThreadMap};
+
 type ErrorCallback = Box<dyn Fn(LuaError) + Send + 'static>;
 
 #[derive(Clone)]
@@ -29,6 +31,16 @@ impl ThreadErrorCallback {
             cb(error.clone());
         }
     }
+
+    /**
+        Calls the error callback for an error in the given thread, unless
+        its errors are currently being handled by something waiting for it.
+    */
+    pub fn call_for_thread(&self, map: &ThreadMap, id: ThreadId, error: &LuaError) {
+        if !map.is_handling_errors(id) {
+            self.call(error);
+        }
+    }
 }
 
 #[allow(clippy::needless_pass_by_value)]
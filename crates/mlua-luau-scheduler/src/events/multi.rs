@@ -20,7 +20,7 @@ struct MultiEventState {
     A single-threaded event signal that can be notified multiple times.
 */
 #[derive(Debug, Clone, Default)]
-pub(crate) struct MultiEvent {
+pub struct MultiEvent {
     state: Rc<MultiEventState>,
 }
 
@@ -28,6 +28,7 @@ impl MultiEvent {
     /**
         Creates a new event.
     */
+    #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
@@ -51,6 +52,7 @@ impl MultiEvent {
     /**
         Creates a listener that implements `Future` and resolves when `notify` is called.
     */
+    #[must_use]
     pub fn listen(&self) -> MultiListener {
         MultiListener {
             state: self.state.clone(),
@@ -60,10 +62,10 @@ impl MultiEvent {
 }
 
 /**
-    A listener future that resolves when the corresponding [`QueueEvent`] is notified.
+    A listener future that resolves when the corresponding [`MultiEvent`] is notified.
 */
 #[derive(Debug)]
-pub(crate) struct MultiListener {
+pub struct MultiListener {
     state: Rc<MultiEventState>,
     generation: u64,
 }
@@ -38,6 +38,7 @@ impl OnceEvent {
     /**
         Creates a new event that can be notified exactly once.
     */
+    #[must_use]
     pub fn new() -> Self {
         let initial_state = OnceEventState::new();
         Self {
@@ -63,6 +64,7 @@ impl OnceEvent {
 
         If `notify` has already been called, the future will resolve immediately.
     */
+    #[must_use]
     pub fn listen(&self) -> OnceListener {
         OnceListener {
             state: self.state.clone(),
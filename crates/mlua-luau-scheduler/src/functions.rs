@@ -3,9 +3,11 @@
 use mlua::prelude::*;
 
 use crate::{
+    context::ThreadContexts,
     error_callback::ThreadErrorCallback,
     queue::{DeferredThreadQueue, SpawnedThreadQueue},
-    threads::{ThreadId, ThreadMap},
+    stats::Metrics,
+    threads::{RunningThreads, ThreadId, ThreadMap},
     traits::LuaSchedulerExt,
     util::{LuaThreadOrFunction, is_poll_pending},
 };
@@ -105,13 +107,30 @@ impl Functions {
             .app_data_ref::<ThreadMap>()
             .expect(ERR_METADATA_NOT_ATTACHED)
             .clone();
+        let running = lua
+            .app_data_ref::<RunningThreads>()
+            .expect(ERR_METADATA_NOT_ATTACHED)
+            .clone();
+        let metrics = lua
+            .app_data_ref::<Metrics>()
+            .expect(ERR_METADATA_NOT_ATTACHED)
+            .clone();
+        let contexts = lua
+            .app_data_ref::<ThreadContexts>()
+            .expect(ERR_METADATA_NOT_ATTACHED)
+            .clone();
 
         let resume_queue = defer_queue.clone();
         let resume_map = thread_map.clone();
+        let resume_metrics = metrics.clone();
+        let resume_contexts = contexts.clone();
         let resume =
             lua.create_function(move |lua, (thread, args): (LuaThread, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_resume").entered();
-                match thread.resume::<LuaMultiValue>(args.clone()) {
+                resume_contexts.inherit(&lua.current_thread(), &thread)?;
+                let result = resume_metrics
+                    .measure(&thread, || thread.resume::<LuaMultiValue>(args.clone()));
+                match result {
                     Ok(v) => {
                         if v.front().is_some_and(is_poll_pending) {
                             // Pending, defer to scheduler and return nil
@@ -158,14 +177,19 @@ impl Functions {
             .into_function()?;
 
         let spawn_map = thread_map.clone();
+        let spawn_metrics = metrics.clone();
+        let spawn_contexts = contexts.clone();
         let spawn = lua.create_function(
             move |lua, (tof, args): (LuaThreadOrFunction, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_spawn").entered();
                 let thread = tof.into_thread(lua)?;
+                spawn_contexts.inherit(&lua.current_thread(), &thread)?;
                 if thread.status() == LuaThreadStatus::Resumable {
                     // NOTE: We need to resume the thread once instantly for correct behavior,
                     // and only if we get the pending value back we can spawn to async executor
-                    match thread.resume::<LuaMultiValue>(args.clone()) {
+                    let result = spawn_metrics
+                        .measure(&thread, || thread.resume::<LuaMultiValue>(args.clone()));
+                    match result {
                         Ok(v) => {
                             if v.front().is_some_and(is_poll_pending) {
                                 spawn_queue.push_item(lua, &thread, args)?;
@@ -180,11 +204,13 @@ impl Functions {
                             }
                         }
                         Err(e) => {
-                            error_callback.call(&e);
                             // Not pending, store the error
                             let id = ThreadId::from(&thread);
                             if spawn_map.is_tracked(id) {
+                                error_callback.call_for_thread(&spawn_map, id, &e);
                                 spawn_map.insert(id, Err(e));
+                            } else {
+                                error_callback.call(&e);
                             }
                         }
                     }
@@ -197,6 +223,7 @@ impl Functions {
             move |lua, (tof, args): (LuaThreadOrFunction, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_defer").entered();
                 let thread = tof.into_thread(lua)?;
+                contexts.inherit(&lua.current_thread(), &thread)?;
                 if thread.status() == LuaThreadStatus::Resumable {
                     defer_queue.push_item(lua, &thread, args)?;
                 }
@@ -209,13 +236,26 @@ impl Functions {
             .get::<LuaTable>("coroutine")?
             .get::<LuaFunction>("close")?;
         let close_key = lua.create_registry_value(close)?;
+        let cancel_map = thread_map.clone();
         let cancel = lua.create_function(move |lua, thread: LuaThread| {
             let _span = tracing::trace_span!("Scheduler::fn_cancel").entered();
+            let was_resumable = thread.status() == LuaThreadStatus::Resumable;
             let close: LuaFunction = lua.registry_value(&close_key)?;
-            match close.call(thread) {
-                Err(LuaError::CoroutineUnresumable) | Ok(()) => Ok(()),
-                Err(e) => Err(e),
+            match close.call(&thread) {
+                Err(LuaError::CoroutineUnresumable) | Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+            // Stop running the thread if it was waiting for something on the executor,
+            // which also means any async function it was waiting for will not be polled
+            let id = ThreadId::from(&thread);
+            running.cancel(id);
+            metrics.remove(id);
+            // Cancelled threads will never finish, so store an error
+            // for them, making sure that nothing waits for them forever
+            if was_resumable && cancel_map.is_tracked(id) {
+                cancel_map.insert(id, Err(LuaError::runtime("Thread was cancelled")));
             }
+            Ok(())
         })?;
 
         let exit_env = lua.create_table_from(vec![
@@ -0,0 +1,71 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use mlua::prelude::*;
+
+type InterruptFn = dyn Fn(&Lua) -> LuaResult<LuaVmState>;
+
+/**
+    An interrupt for the Lua state of a scheduler, which is chained with the
+    interrupt used by the watchdog of the scheduler, instead of replacing it.
+*/
+#[derive(Clone)]
+pub(crate) struct SchedulerInterrupt {
+    inner: Rc<RefCell<Option<Rc<InterruptFn>>>>,
+    chained: Rc<Cell<bool>>,
+}
+
+impl SchedulerInterrupt {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(None)),
+            chained: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn replace(&self, lua: &Lua, callback: impl Fn(&Lua) -> LuaResult<LuaVmState> + 'static) {
+        self.inner.borrow_mut().replace(Rc::new(callback));
+        // NOTE: A chained interrupt is called by the interrupt that is already
+        // set, so setting it again here would replace the one chaining to it
+        if !self.chained.get() {
+            self.install(lua);
+        }
+    }
+
+    pub fn call(&self, lua: &Lua) -> LuaResult<LuaVmState> {
+        // NOTE: The callback may replace itself, so we must not
+        // hold on to the borrow while it is being called
+        let callback = self.inner.borrow().clone();
+        match callback {
+            Some(callback) => callback(lua),
+            None => Ok(LuaVmState::Continue),
+        }
+    }
+
+    /**
+        Sets the given interrupt for the Lua state, which must call [`SchedulerInterrupt::call`].
+    */
+    pub fn chain(&self, lua: &Lua, interrupt: impl Fn(&Lua) -> LuaResult<LuaVmState> + 'static) {
+        self.chained.set(true);
+        lua.set_interrupt(interrupt);
+    }
+
+    /**
+        Stops chaining, and sets only this interrupt for the Lua state again, if any.
+    */
+    pub fn unchain(&self, lua: &Lua) {
+        self.chained.set(false);
+        self.install(lua);
+    }
+
+    fn install(&self, lua: &Lua) {
+        if self.inner.borrow().is_some() {
+            let this = self.clone();
+            lua.set_interrupt(move |lua| this.call(lua));
+        } else {
+            lua.remove_interrupt();
+        }
+    }
+}
@@ -1,18 +1,29 @@
 #![allow(clippy::cargo_common_metadata)]
 
+mod clock;
+mod context;
 mod error_callback;
 mod events;
 mod exit;
 mod functions;
+mod interrupt;
+mod priority;
 mod queue;
 mod scheduler;
+mod stats;
 mod status;
 mod threads;
 mod traits;
 mod util;
+mod watchdog;
 
+pub use clock::Clock;
+pub use context::Context;
+pub use events::{MultiEvent, MultiListener, OnceEvent, OnceListener};
 pub use functions::Functions;
+pub use priority::Priority;
 pub use scheduler::Scheduler;
+pub use stats::{SchedulerStats, ThreadState, ThreadStats};
 pub use status::Status;
 pub use threads::ThreadId;
 pub use traits::{IntoLuaThread, LuaSchedulerExt, LuaSpawnExt};
@@ -0,0 +1,82 @@
+use mlua::prelude::*;
+
+use crate::util::create_weak_keys_table;
+
+/**
+    The priority of a Lua thread, deciding the order in which queued threads are resumed.
+
+    Threads that are waiting in the queues of a [`Scheduler`](crate::Scheduler) are always
+    resumed in order of priority, highest first, and in the order they were queued otherwise.
+    When a resume budget is set using [`Scheduler::set_resume_budget`](crate::Scheduler::set_resume_budget),
+    this means that threads with a higher priority are never held back by a long queue of
+    threads with a lower priority.
+
+    Note that priorities only apply to threads waiting in the queues of the scheduler, and
+    not to threads waiting for futures, such as sleeps, which are always resumed in order.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    /**
+        All priorities, from highest to lowest.
+    */
+    pub(crate) const DESCENDING: [Self; 3] = [Self::High, Self::Normal, Self::Low];
+
+    pub(crate) const fn index(self) -> usize {
+        match self {
+            Self::Low => 0,
+            Self::Normal => 1,
+            Self::High => 2,
+        }
+    }
+
+    const fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0 => Some(Self::Low),
+            1 => Some(Self::Normal),
+            2 => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+/**
+    Storage for the priorities of all threads in a scheduler.
+
+    Priorities are stored in a table with weak keys, so that the priority
+    of a thread is removed as soon as the thread is garbage collected.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct ThreadPriorities {
+    map: LuaTable,
+}
+
+impl ThreadPriorities {
+    pub fn new(lua: &Lua) -> LuaResult<Self> {
+        let map = create_weak_keys_table(lua)?;
+        Ok(Self { map })
+    }
+
+    pub fn get(&self, thread: &LuaThread) -> Priority {
+        self.map
+            .raw_get::<Option<usize>>(thread)
+            .ok()
+            .flatten()
+            .and_then(Priority::from_index)
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, thread: &LuaThread, priority: Priority) -> LuaResult<()> {
+        if priority == Priority::default() {
+            self.map.raw_set(thread, LuaNil)
+        } else {
+            self.map.raw_set(thread, priority.index())
+        }
+    }
+}
@@ -53,3 +53,33 @@ impl<'fut> FuturesQueue<'fut> {
         }
     }
 }
+
+/**
+    Queue for storing local background futures.
+
+    Background futures run on their own executor, and unlike futures in
+    the regular [`FuturesQueue`], they do not keep the scheduler running.
+*/
+#[derive(Clone)]
+pub(crate) struct BackgroundFuturesQueue<'fut> {
+    inner: FuturesQueue<'fut>,
+}
+
+impl<'fut> BackgroundFuturesQueue<'fut> {
+    pub fn new() -> Self {
+        let inner = FuturesQueue::new();
+        Self { inner }
+    }
+
+    pub fn push_item(&self, fut: impl Future<Output = ()> + 'fut) {
+        self.inner.push_item(fut);
+    }
+
+    pub fn take_items(&self) -> Vec<LocalBoxFuture<'fut>> {
+        self.inner.take_items()
+    }
+
+    pub async fn wait_for_item(&self) {
+        self.inner.wait_for_item().await;
+    }
+}
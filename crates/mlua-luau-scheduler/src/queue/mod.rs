@@ -4,5 +4,5 @@ mod spawned;
 mod threads;
 
 pub(crate) use self::deferred::DeferredThreadQueue;
-pub(crate) use self::futures::FuturesQueue;
+pub(crate) use self::futures::{BackgroundFuturesQueue, FuturesQueue};
 pub(crate) use self::spawned::SpawnedThreadQueue;
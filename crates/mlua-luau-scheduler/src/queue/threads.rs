@@ -1,23 +1,30 @@
 #![allow(clippy::inline_always)]
 
-use std::{cell::RefCell, mem, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
 
 use mlua::prelude::*;
 
-use crate::{threads::ThreadId, traits::IntoLuaThread};
+use crate::{
+    priority::{Priority, ThreadPriorities},
+    threads::ThreadId,
+    traits::IntoLuaThread,
+};
 
 use crate::events::MultiEvent;
 
+type ThreadQueueItem = (LuaThread, LuaMultiValue);
+
 #[derive(Debug)]
 struct ThreadQueueInner {
-    queue: RefCell<Vec<(LuaThread, LuaMultiValue)>>,
+    // One queue per priority, indexed by Priority::index
+    queues: RefCell<[VecDeque<ThreadQueueItem>; 3]>,
     event: MultiEvent,
 }
 
 impl ThreadQueueInner {
     fn new() -> Self {
         Self {
-            queue: RefCell::new(Vec::new()),
+            queues: RefCell::new(Default::default()),
             event: MultiEvent::new(),
         }
     }
@@ -28,6 +35,9 @@ impl ThreadQueueInner {
 
     Provides methods for pushing and draining the queue, as
     well as listening for new items being pushed to the queue.
+
+    Threads are queued according to their [`Priority`], and are
+    taken from the queue in the order that they were pushed in.
 */
 #[derive(Debug, Clone)]
 pub(crate) struct ThreadQueue {
@@ -49,30 +59,52 @@ impl ThreadQueue {
         let thread = thread.into_lua_thread(lua)?;
         let args = args.into_lua_multi(lua)?;
 
+        let priority = lua
+            .app_data_ref::<ThreadPriorities>()
+            .map(|priorities| priorities.get(&thread))
+            .unwrap_or_default();
+
         tracing::trace!("pushing item to queue with {} args", args.len());
         let id = ThreadId::from(&thread);
 
-        self.inner.queue.borrow_mut().push((thread, args));
+        self.inner.queues.borrow_mut()[priority.index()].push_back((thread, args));
         self.inner.event.notify();
 
         Ok(id)
     }
 
+    /**
+        Takes up to `limit` items with the given priority from the queue.
+    */
     #[inline(always)]
-    pub fn take_items(&self) -> Vec<(LuaThread, LuaMultiValue)> {
-        let mut queue = self.inner.queue.borrow_mut();
-        mem::take(&mut *queue)
+    pub fn take_items(&self, priority: Priority, limit: usize) -> Vec<ThreadQueueItem> {
+        let mut queues = self.inner.queues.borrow_mut();
+        let queue = &mut queues[priority.index()];
+        let count = queue.len().min(limit);
+        queue.drain(..count).collect()
     }
 
     #[inline(always)]
     pub async fn wait_for_item(&self) {
-        if self.inner.queue.borrow().is_empty() {
+        if self.is_empty() {
             self.inner.event.listen().await;
         }
     }
 
+    /**
+        Returns all threads in the queue, in the order that they will be taken from it.
+    */
+    pub fn threads(&self) -> Vec<LuaThread> {
+        let queues = self.inner.queues.borrow();
+        Priority::DESCENDING
+            .iter()
+            .flat_map(|priority| queues[priority.index()].iter())
+            .map(|(thread, _)| thread.clone())
+            .collect()
+    }
+
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
-        self.inner.queue.borrow().is_empty()
+        self.inner.queues.borrow().iter().all(VecDeque::is_empty)
     }
 }
@@ -1,26 +1,37 @@
 #![allow(clippy::module_name_repetitions)]
 
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
+    fmt::Write,
     rc::Rc,
     sync::{Arc, Weak as WeakArc},
+    task::Poll,
     thread::panicking,
+    time::Duration,
 };
 
-use futures_lite::prelude::*;
+use futures_lite::{
+    future::{poll_fn, yield_now},
+    prelude::*,
+};
 use mlua::prelude::*;
 
 use async_executor::{Executor, LocalExecutor};
 use tracing::{Instrument, debug, instrument, trace, trace_span};
 
 use crate::{
+    clock::Clock,
+    context::ThreadContexts,
     error_callback::ThreadErrorCallback,
     exit::Exit,
-    queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
+    interrupt::SchedulerInterrupt,
+    priority::{Priority, ThreadPriorities},
+    queue::{BackgroundFuturesQueue, DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
+    stats::{Metrics, SchedulerStats},
     status::Status,
-    threads::{ThreadId, ThreadMap},
+    threads::{RunningThreads, ThreadId, ThreadMap},
     traits::IntoLuaThread,
-    util::run_until_yield,
+    util::{current_traceback, run_until_yield, traceback},
 };
 
 const ERR_METADATA_ALREADY_ATTACHED: &str = "\
@@ -38,6 +49,16 @@ const ERR_SET_CALLBACK_WHEN_RUNNING: &str = "\
 Cannot set error callback when scheduler is running!\
 ";
 
+const ERR_SET_WATCHDOG_WHEN_RUNNING: &str = "\
+Cannot set watchdog when scheduler is running!\
+";
+
+const ERR_SET_VIRTUAL_TIME_WHEN_RUNNING: &str = "\
+Cannot enable or disable virtual time when scheduler is running!\
+";
+
+type WatchdogCallback = Rc<dyn Fn(&str) + Send + 'static>;
+
 /**
     A scheduler for running Lua threads and async tasks.
 */
@@ -48,8 +69,15 @@ pub struct Scheduler {
     queue_defer: DeferredThreadQueue,
     error_callback: ThreadErrorCallback,
     thread_map: ThreadMap,
+    running: RunningThreads,
+    metrics: Metrics,
+    clock: Clock,
+    resume_budget: Rc<Cell<Option<usize>>>,
     status: Rc<Cell<Status>>,
     exit: Exit,
+    interrupt: SchedulerInterrupt,
+    roots: Rc<RefCell<Vec<LuaThread>>>,
+    watchdog: Rc<RefCell<Option<(Duration, WatchdogCallback)>>>,
 }
 
 impl Scheduler {
@@ -60,7 +88,8 @@ impl Scheduler {
 
         # Panics
 
-        Panics if the given Lua state already has a scheduler attached to it.
+        Panics if the given Lua state already has a scheduler attached to it,
+        or if the Lua state is out of memory.
     */
     #[must_use]
     pub fn new(lua: Lua) -> Scheduler {
@@ -68,7 +97,14 @@ impl Scheduler {
         let queue_defer = DeferredThreadQueue::new();
         let error_callback = ThreadErrorCallback::default();
         let result_map = ThreadMap::new();
+        let running = RunningThreads::new();
+        let status = Rc::new(Cell::new(Status::NotStarted));
+        let metrics = Metrics::new(Rc::clone(&status));
+        let clock = Clock::new();
+        let contexts = ThreadContexts::new(&lua).expect("failed to create thread contexts");
+        let priorities = ThreadPriorities::new(&lua).expect("failed to create thread priorities");
         let exit = Exit::new();
+        let interrupt = SchedulerInterrupt::new();
 
         assert!(
             lua.app_data_ref::<SpawnedThreadQueue>().is_none(),
@@ -86,18 +122,46 @@ impl Scheduler {
             lua.app_data_ref::<ThreadMap>().is_none(),
             "{ERR_METADATA_ALREADY_ATTACHED}"
         );
+        assert!(
+            lua.app_data_ref::<RunningThreads>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+        assert!(
+            lua.app_data_ref::<Metrics>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+        assert!(
+            lua.app_data_ref::<Clock>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+        assert!(
+            lua.app_data_ref::<ThreadContexts>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+        assert!(
+            lua.app_data_ref::<ThreadPriorities>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
         assert!(
             lua.app_data_ref::<Exit>().is_none(),
             "{ERR_METADATA_ALREADY_ATTACHED}"
         );
+        assert!(
+            lua.app_data_ref::<SchedulerInterrupt>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
 
         lua.set_app_data(queue_spawn.clone());
         lua.set_app_data(queue_defer.clone());
         lua.set_app_data(error_callback.clone());
         lua.set_app_data(result_map.clone());
+        lua.set_app_data(running.clone());
+        lua.set_app_data(metrics.clone());
+        lua.set_app_data(clock.clone());
+        lua.set_app_data(contexts);
+        lua.set_app_data(priorities);
         lua.set_app_data(exit.clone());
-
-        let status = Rc::new(Cell::new(Status::NotStarted));
+        lua.set_app_data(interrupt.clone());
 
         Scheduler {
             lua,
@@ -105,8 +169,15 @@ impl Scheduler {
             queue_defer,
             error_callback,
             thread_map: result_map,
+            running,
+            metrics,
+            clock,
+            resume_budget: Rc::default(),
             status,
             exit,
+            interrupt,
+            roots: Rc::default(),
+            watchdog: Rc::default(),
         }
     }
 
@@ -126,6 +197,17 @@ impl Scheduler {
         self.status.get()
     }
 
+    /**
+        Returns a snapshot of the current state of this scheduler, including
+        the number of queued and waiting threads, pending futures, and
+        statistics for each of the threads that are currently suspended.
+    */
+    #[must_use]
+    pub fn stats(&self) -> SchedulerStats {
+        self.metrics
+            .snapshot(&self.queue_spawn, &self.queue_defer, &self.running)
+    }
+
     /**
         Sets the error callback for this scheduler.
 
@@ -162,6 +244,108 @@ impl Scheduler {
         self.error_callback.clear();
     }
 
+    /**
+        Sets a watchdog for this scheduler.
+
+        The watchdog calls the given callback with a report, including a stack traceback,
+        whenever a Lua thread has been running for longer than the given timeout without
+        yielding. Each such thread is reported once per resume, and is not interrupted.
+
+        Overwrites any previous watchdog.
+
+        The watchdog uses the interrupt of the Lua state while the scheduler is running, so
+        any other interrupt must be set using [`LuaSchedulerExt::set_scheduler_interrupt`],
+        which is chained with the watchdog - an interrupt set directly using
+        [`Lua::set_interrupt`] is replaced while running, and removed afterwards.
+
+        [`LuaSchedulerExt::set_scheduler_interrupt`]: crate::LuaSchedulerExt::set_scheduler_interrupt
+
+        # Panics
+
+        Panics if the scheduler is currently running.
+    */
+    pub fn set_watchdog(&self, timeout: Duration, callback: impl Fn(&str) + Send + 'static) {
+        assert!(
+            !self.status().is_running(),
+            "{ERR_SET_WATCHDOG_WHEN_RUNNING}"
+        );
+        self.watchdog.replace(Some((timeout, Rc::new(callback))));
+    }
+
+    /**
+        Removes the watchdog for this scheduler, if one was set.
+
+        # Panics
+
+        Panics if the scheduler is currently running.
+    */
+    pub fn remove_watchdog(&self) {
+        assert!(
+            !self.status().is_running(),
+            "{ERR_SET_WATCHDOG_WHEN_RUNNING}"
+        );
+        self.watchdog.take();
+    }
+
+    /**
+        Enables or disables virtual time for this scheduler.
+
+        When virtual time is enabled, time does not pass while any Lua threads or futures
+        are able to make progress. Once none of them can, the [`Clock`] of this scheduler
+        skips ahead to the next pending sleep, which makes code that waits for time to pass
+        run instantly, and deterministically, while still running sleeps in order.
+
+        Time may also be advanced manually using [`Clock::advance`].
+
+        # Panics
+
+        Panics if the scheduler is currently running.
+    */
+    pub fn set_virtual_time(&self, enabled: bool) {
+        assert!(
+            !self.status().is_running(),
+            "{ERR_SET_VIRTUAL_TIME_WHEN_RUNNING}"
+        );
+        self.clock.set_virtual(enabled);
+    }
+
+    /**
+        Sets the resume budget for this scheduler.
+
+        The resume budget is the maximum number of queued Lua threads that are resumed
+        during a single tick of the scheduler. Any other threads stay in the queues until
+        the next tick, and futures, such as timers, get to make progress in between ticks.
+        This prevents long bursts of threads from starving everything else, for example
+        when a server receives a flood of messages, at some cost to overall throughput.
+
+        Queued threads with a higher [`Priority`] are always resumed first.
+
+        By default, there is no resume budget, and all queued threads are resumed every tick.
+
+        # Panics
+
+        Panics if the given budget is zero.
+    */
+    pub fn set_resume_budget(&self, budget: usize) {
+        assert!(budget > 0, "resume budget must be greater than zero");
+        self.resume_budget.set(Some(budget));
+    }
+
+    /**
+        Removes the resume budget for this scheduler, if one was set.
+    */
+    pub fn remove_resume_budget(&self) {
+        self.resume_budget.set(None);
+    }
+
+    /**
+        Returns the clock used by this scheduler.
+    */
+    #[must_use]
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
     /**
         Gets the exit code for this scheduler, if one has been set.
     */
@@ -199,6 +383,8 @@ impl Scheduler {
         thread: impl IntoLuaThread,
         args: impl IntoLuaMulti,
     ) -> LuaResult<ThreadId> {
+        let thread = thread.into_lua_thread(&self.lua)?;
+        self.roots.borrow_mut().push(thread.clone());
         let id = self.queue_spawn.push_item(&self.lua, thread, args)?;
         self.thread_map.track(id);
         Ok(id)
@@ -226,6 +412,8 @@ impl Scheduler {
         thread: impl IntoLuaThread,
         args: impl IntoLuaMulti,
     ) -> LuaResult<ThreadId> {
+        let thread = thread.into_lua_thread(&self.lua)?;
+        self.roots.borrow_mut().push(thread.clone());
         let id = self.queue_defer.push_item(&self.lua, thread, args)?;
         self.thread_map.track(id);
         Ok(id)
@@ -264,6 +452,11 @@ impl Scheduler {
 
         This will return instantly if no threads have been scheduled.
 
+        If all remaining Lua threads are blocked waiting for each other, and nothing else
+        is left that could wake them up, the scheduler stops early and reports the deadlock
+        to the error callback, listing each thread and what it is waiting for. Threads pushed
+        directly to this scheduler that yield, but are never resumed, are also reported.
+
         Note that the given Lua state must be the same one that was
         used to create this scheduler, otherwise this method will panic.
 
@@ -290,8 +483,10 @@ impl Scheduler {
             saving a tiny bit of processing from going on the Lua executor itself.
         */
         let local_exec = LocalExecutor::new();
+        let background_exec = LocalExecutor::new();
         let main_exec = Arc::new(Executor::new());
         let fut_queue = FuturesQueue::new();
+        let bg_queue = BackgroundFuturesQueue::new();
 
         /*
             Store the main executor and queue in Lua, so that they may be used with LuaSchedulerExt.
@@ -307,9 +502,14 @@ impl Scheduler {
             self.lua.app_data_ref::<FuturesQueue>().is_none(),
             "{ERR_METADATA_ALREADY_ATTACHED}"
         );
+        assert!(
+            self.lua.app_data_ref::<BackgroundFuturesQueue>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
 
         self.lua.set_app_data(Arc::downgrade(&main_exec));
         self.lua.set_app_data(fut_queue.clone());
+        self.lua.set_app_data(bg_queue.clone());
 
         /*
             Manually tick the Lua executor, while running under the main executor.
@@ -318,49 +518,58 @@ impl Scheduler {
             1. The exit event is triggered by setting an exit code
             2. A Lua thread is available to run on the spawned queue
             3. A Lua thread is available to run on the deferred queue
-            4. A new thread-local future is available to run on the local executor
-            5. Task(s) scheduled on the Lua executor have made progress and should be polled again
+            4. A new thread-local future is available to run on the local (or background) executor
+            5. Task(s) scheduled on the Lua (or background) executor have made progress and should be polled again
+            6. Nothing else can make progress, and virtual time should skip ahead to the next pending sleep
+            7. Nothing else can make progress, and all remaining Lua threads are blocked on each other
 
             This ordering is vital to ensure that we don't accidentally exit the main loop
             when there are new Lua threads to enqueue and potentially more work to be done.
+
+            Queued Lua threads are taken in order of priority, and limited by the resume budget, if
+            any - when threads are left in the queues because of the budget, the executors are ticked
+            before taking any more of them, so that the threads can not starve other tasks.
+
+            Note that the background executor is never checked when deciding if we are done,
+            any background futures still pending at that point will simply be dropped.
         */
+        let deadlocked = Cell::new(false);
         let fut = async {
-            let result_map = self.thread_map.clone();
+            let result_map = &self.thread_map;
+            let metrics = &self.metrics;
             let process_thread = |thread: LuaThread, args| {
                 // NOTE: Thread may have been cancelled from Lua
                 // before we got here, so we need to check it again
                 if thread.status() == LuaThreadStatus::Resumable {
-                    // Check if we should be tracking this thread
                     let id = ThreadId::from(&thread);
-                    let id_tracked = result_map.is_tracked(id);
-                    let result_map_inner = if id_tracked {
-                        Some(result_map.clone())
-                    } else {
-                        None
-                    };
+                    let cancelled = self.running.insert(&thread);
                     // Create our future which will run the thread and store its final result
                     let fut = async move {
-                        if id_tracked {
-                            // Run until yield and check if we got a final result
-                            if let Some(res) = run_until_yield(thread.clone(), args).await {
-                                if let Err(e) = res.as_ref() {
-                                    self.error_callback.call(e);
-                                }
-                                if thread.status() != LuaThreadStatus::Resumable {
-                                    result_map_inner.unwrap().insert(id, res);
-                                }
+                        // Run until yield and check if we got a final result
+                        let Some(res) = run_until_yield(thread.clone(), args, metrics).await else {
+                            return;
+                        };
+                        // NOTE: Threads may start being tracked while they are running, such
+                        // as once something waits for them, so we check this only once done
+                        if result_map.is_tracked(id) {
+                            if let Err(e) = res.as_ref() {
+                                self.error_callback.call_for_thread(result_map, id, e);
                             }
-                        } else {
-                            // Just run until yield
-                            if let Some(res) = run_until_yield(thread, args).await
-                                && let Err(e) = res.as_ref()
-                            {
-                                self.error_callback.call(e);
+                            if thread.status() != LuaThreadStatus::Resumable {
+                                result_map.insert(id, res);
                             }
+                        } else if let Err(e) = res.as_ref() {
+                            self.error_callback.call(e);
                         }
                     };
-                    // Spawn it on the executor
-                    local_exec.spawn(fut).detach();
+                    // Spawn it on the executor, stopping early if the thread gets cancelled
+                    let running = self.running.clone();
+                    local_exec
+                        .spawn(async move {
+                            fut.or(cancelled).await;
+                            running.remove(id);
+                        })
+                        .detach();
                 }
             };
 
@@ -369,25 +578,52 @@ impl Scheduler {
                 let fut_spawn = self.queue_spawn.wait_for_item(); // 2
                 let fut_defer = self.queue_defer.wait_for_item(); // 3
                 let fut_futs = fut_queue.wait_for_item(); // 4
+                let fut_bg_futs = bg_queue.wait_for_item(); // 4
 
                 // 5
                 let mut num_processed = 0;
                 let span_tick = trace_span!("Scheduler::tick");
                 let fut_tick = async {
-                    local_exec.tick().await;
+                    local_exec.tick().or(background_exec.tick()).await;
                     // NOTE: Try to do as much work as possible instead of just a single tick()
                     num_processed += 1;
-                    while local_exec.try_tick() {
+                    while local_exec.try_tick() || background_exec.try_tick() {
                         num_processed += 1;
                     }
                 };
 
-                // 1 + 2 + 3 + 4 + 5
+                // 6
+                let fut_clock = poll_fn(|_| {
+                    // NOTE: Just like below, this only gets polled once nothing else can make
+                    // progress, which is exactly when virtual time should skip ahead
+                    if self.clock.advance_idle() {
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    }
+                });
+
+                // 7
+                let fut_deadlock = poll_fn(|_| {
+                    // NOTE: This only gets polled once all of the above are pending,
+                    // meaning no Lua threads or futures can currently make progress
+                    if self.metrics.is_deadlocked(&self.running) {
+                        deadlocked.set(true);
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    }
+                });
+
+                // 1 + 2 + 3 + 4 + 5 + 6 + 7
                 fut_exit
                     .or(fut_spawn)
                     .or(fut_defer)
                     .or(fut_futs)
+                    .or(fut_bg_futs)
                     .or(fut_tick.instrument(span_tick.or_current()))
+                    .or(fut_clock)
+                    .or(fut_deadlock)
                     .await;
 
                 // Check if we should exit
@@ -395,33 +631,73 @@ impl Scheduler {
                     debug!("exit signal received");
                     break;
                 }
+                if deadlocked.get() {
+                    debug!("deadlock detected");
+                    self.report_deadlock();
+                    break;
+                }
 
-                // Process spawned threads first, then deferred threads, then futures
+                // Process spawned threads first, then deferred threads, then futures,
+                // with threads of higher priorities always being processed first
                 let mut num_spawned = 0;
                 let mut num_deferred = 0;
                 let mut num_futures = 0;
-                {
-                    let _span = trace_span!("Scheduler::drain_spawned").entered();
-                    for (thread, args) in self.queue_spawn.take_items() {
-                        process_thread(thread, args);
-                        num_spawned += 1;
+                let mut budget = self.resume_budget.get().unwrap_or(usize::MAX);
+                for priority in Priority::DESCENDING {
+                    {
+                        let _span = trace_span!("Scheduler::drain_spawned").entered();
+                        for (thread, args) in self.queue_spawn.take_items(priority, budget) {
+                            process_thread(thread, args);
+                            num_spawned += 1;
+                            budget -= 1;
+                        }
                     }
-                }
-                {
-                    let _span = trace_span!("Scheduler::drain_deferred").entered();
-                    for (thread, args) in self.queue_defer.take_items() {
-                        process_thread(thread, args);
-                        num_deferred += 1;
+                    {
+                        let _span = trace_span!("Scheduler::drain_deferred").entered();
+                        for (thread, args) in self.queue_defer.take_items(priority, budget) {
+                            process_thread(thread, args);
+                            num_deferred += 1;
+                            budget -= 1;
+                        }
                     }
                 }
                 {
                     let _span = trace_span!("Scheduler::drain_futures").entered();
                     for fut in fut_queue.take_items() {
-                        local_exec.spawn(fut).detach();
+                        let pending = self.metrics.pending_future();
+                        let waker = self.metrics.pending_waker();
+                        local_exec
+                            .spawn(async move {
+                                fut.await;
+                                drop((pending, waker));
+                            })
+                            .detach();
+                        num_futures += 1;
+                    }
+                    for fut in bg_queue.take_items() {
+                        let pending = self.metrics.pending_future();
+                        background_exec
+                            .spawn(async move {
+                                fut.await;
+                                drop(pending);
+                            })
+                            .detach();
                         num_futures += 1;
                     }
                 }
 
+                // Threads left in the queues because of the resume budget would otherwise get taken
+                // right away during the next tick, so we let everything else make progress first
+                if !self.queue_spawn.is_empty() || !self.queue_defer.is_empty() {
+                    {
+                        let _span = trace_span!("Scheduler::tick_budget").entered();
+                        while local_exec.try_tick() || background_exec.try_tick() {
+                            num_processed += 1;
+                        }
+                    }
+                    yield_now().await;
+                }
+
                 // Empty executor = we didn't spawn any new Lua tasks
                 // above, and there are no remaining tasks to run later
                 let completed = local_exec.is_empty()
@@ -440,11 +716,47 @@ impl Scheduler {
             }
         };
 
+        // Watch for threads that run for too long without yielding, if requested,
+        // chaining to the interrupt that was set for the scheduler, if any
+        let watchdog = self.watchdog.borrow().clone();
+        let watchdog_guard = watchdog.map(|(timeout, callback)| {
+            let watchdog = self.metrics.watchdog().clone();
+            let guard = watchdog.start(timeout);
+            let interrupt = self.interrupt.clone();
+            self.interrupt.chain(&self.lua, move |lua| {
+                if let Some(elapsed) = watchdog.take_overdue() {
+                    callback(&format!(
+                        "Thread has been running for {:.1} seconds without yielding\n{}",
+                        elapsed.as_secs_f64(),
+                        indent(&current_traceback(lua)),
+                    ));
+                }
+                interrupt.call(lua)
+            });
+            guard
+        });
+
         // Run the executor inside a span until all lua threads complete
         self.set_status(Status::Running);
         main_exec.run(fut).await;
         self.set_status(Status::Completed);
 
+        if watchdog_guard.is_some() {
+            self.interrupt.unchain(&self.lua);
+        }
+        drop(watchdog_guard);
+
+        // Threads that we were asked to run, but that yielded with nothing left to
+        // resume them, would otherwise silently never finish, so we report them here
+        let roots = self.roots.take();
+        if self.exit.get().is_none() && !deadlocked.get() {
+            for thread in roots {
+                if thread.status() == LuaThreadStatus::Resumable {
+                    self.report_unfinished(&thread);
+                }
+            }
+        }
+
         // Clean up
         self.lua
             .remove_app_data::<WeakArc<Executor>>()
@@ -452,9 +764,65 @@ impl Scheduler {
         self.lua
             .remove_app_data::<FuturesQueue>()
             .expect(ERR_METADATA_REMOVED);
+        self.lua
+            .remove_app_data::<BackgroundFuturesQueue>()
+            .expect(ERR_METADATA_REMOVED);
+    }
+
+    /**
+        Reports all threads waiting on the executor as deadlocked, and stores
+        the deadlock as the result of any of those threads that are tracked.
+    */
+    fn report_deadlock(&self) {
+        let stats = self.stats();
+        let mut message = String::from(
+            "Deadlock detected - all threads are waiting for each other, \
+            and nothing is left that could wake them up",
+        );
+        for (index, thread) in stats.threads.iter().enumerate() {
+            let reason = thread.blocked_on.as_deref().unwrap_or("an unknown event");
+            write!(message, "\n\nThread {} is waiting for {reason}", index + 1).ok();
+            if let Ok(trace) = thread.traceback(&self.lua) {
+                write!(message, "\n{}", indent(&trace)).ok();
+            }
+        }
+
+        let error = LuaError::runtime(message);
+        self.error_callback.call(&error);
+        for thread in &stats.threads {
+            let id = ThreadId::from(&thread.thread);
+            if self.thread_map.is_tracked(id) {
+                self.thread_map.insert(id, Err(error.clone()));
+            }
+        }
+    }
+
+    /**
+        Reports a thread that yielded, but was never resumed, and stores
+        the report as the result of the thread, if it is tracked.
+    */
+    fn report_unfinished(&self, thread: &LuaThread) {
+        let mut message = String::from("Thread yielded, but nothing is left to resume it");
+        if let Ok(trace) = traceback(&self.lua, thread) {
+            write!(message, "\n{}", indent(&trace)).ok();
+        }
+
+        let error = LuaError::runtime(message);
+        self.error_callback.call(&error);
+        let id = ThreadId::from(thread);
+        if self.thread_map.is_tracked(id) {
+            self.thread_map.insert(id, Err(error));
+        }
     }
 }
 
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("    {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 impl Drop for Scheduler {
     fn drop(&mut self) {
         if panicking() {
@@ -464,7 +832,13 @@ impl Drop for Scheduler {
             self.lua.remove_app_data::<DeferredThreadQueue>();
             self.lua.remove_app_data::<ThreadErrorCallback>();
             self.lua.remove_app_data::<ThreadMap>();
+            self.lua.remove_app_data::<RunningThreads>();
+            self.lua.remove_app_data::<Metrics>();
+            self.lua.remove_app_data::<Clock>();
+            self.lua.remove_app_data::<ThreadContexts>();
+            self.lua.remove_app_data::<ThreadPriorities>();
             self.lua.remove_app_data::<Exit>();
+            self.lua.remove_app_data::<SchedulerInterrupt>();
         } else {
             // In any other case we panic if metadata was removed incorrectly
             self.lua
@@ -479,9 +853,27 @@ impl Drop for Scheduler {
             self.lua
                 .remove_app_data::<ThreadMap>()
                 .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<RunningThreads>()
+                .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<Metrics>()
+                .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<Clock>()
+                .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<ThreadContexts>()
+                .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<ThreadPriorities>()
+                .expect(ERR_METADATA_REMOVED);
             self.lua
                 .remove_app_data::<Exit>()
                 .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<SchedulerInterrupt>()
+                .expect(ERR_METADATA_REMOVED);
         }
     }
 }
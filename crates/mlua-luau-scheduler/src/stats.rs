@@ -0,0 +1,312 @@
+#![allow(clippy::module_name_repetitions)]
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use mlua::prelude::*;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    queue::{DeferredThreadQueue, SpawnedThreadQueue},
+    status::Status,
+    threads::{RunningThreads, ThreadId},
+    util::traceback,
+    watchdog::Watchdog,
+};
+
+/**
+    The reason that a thread is currently suspended in a scheduler.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThreadState {
+    /// The thread is queued to be resumed, after being spawned.
+    Spawned,
+    /// The thread is queued to be resumed, after being deferred.
+    Deferred,
+    /// The thread is waiting for an async function to complete.
+    Waiting,
+}
+
+impl ThreadState {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Spawned => "spawned",
+            Self::Deferred => "deferred",
+            Self::Waiting => "waiting",
+        }
+    }
+}
+
+/**
+    Statistics for a single thread that is currently suspended in a scheduler.
+*/
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ThreadStats {
+    /// The suspended thread.
+    pub thread: LuaThread,
+    /// The reason that the thread is suspended.
+    pub state: ThreadState,
+    /// The number of times the thread has been resumed.
+    pub resumes: u64,
+    /// The total time spent running the thread, including any threads it resumed.
+    pub cpu_time: Duration,
+    /// What the thread is blocked on, if it is waiting for other Lua threads.
+    ///
+    /// See [`LuaSchedulerExt::blocked_on`](crate::LuaSchedulerExt::blocked_on) for more information.
+    pub blocked_on: Option<String>,
+}
+
+impl ThreadStats {
+    /**
+        Returns the stack traceback of the thread, omitting
+        any internal frames used for polling async functions.
+
+        # Errors
+
+        Errors if the `debug.traceback` global function is missing.
+    */
+    pub fn traceback(&self, lua: &Lua) -> LuaResult<String> {
+        traceback(lua, &self.thread)
+    }
+}
+
+/**
+    A snapshot of the current state of a scheduler.
+*/
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SchedulerStats {
+    /// The current status of the scheduler.
+    pub status: Status,
+    /// The number of threads queued to be resumed, after being spawned.
+    pub spawned: usize,
+    /// The number of threads queued to be resumed, after being deferred.
+    pub deferred: usize,
+    /// The number of threads waiting for an async function to complete.
+    pub waiting: usize,
+    /// The number of threads that are currently running.
+    pub running: usize,
+    /// The number of futures spawned using [`LuaSpawnExt`](crate::LuaSpawnExt) that are still pending.
+    pub futures: usize,
+    /// Statistics for each of the suspended threads - spawned, deferred, and waiting.
+    pub threads: Vec<ThreadStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ThreadMetrics {
+    resumes: u64,
+    cpu_time: Duration,
+}
+
+/**
+    Metrics collected by a scheduler while running, used to create [`SchedulerStats`].
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Metrics {
+    status: Rc<Cell<Status>>,
+    threads: Rc<RefCell<FxHashMap<ThreadId, ThreadMetrics>>>,
+    resuming: Rc<RefCell<Vec<ThreadId>>>,
+    blocked: Rc<RefCell<FxHashMap<ThreadId, Vec<String>>>>,
+    futures: Rc<Cell<usize>>,
+    // Pending futures that may wake up threads blocked on other threads
+    wakers: Rc<Cell<usize>>,
+    watchdog: Watchdog,
+}
+
+impl Metrics {
+    pub fn new(status: Rc<Cell<Status>>) -> Self {
+        Self {
+            status,
+            threads: Rc::default(),
+            resuming: Rc::default(),
+            blocked: Rc::default(),
+            futures: Rc::default(),
+            wakers: Rc::default(),
+            watchdog: Watchdog::new(),
+        }
+    }
+
+    /**
+        Measures a single resume of the given thread, running the given function.
+
+        Metrics for the thread are removed once it is no longer resumable.
+    */
+    pub fn measure<R>(&self, thread: &LuaThread, f: impl FnOnce() -> R) -> R {
+        let id = ThreadId::from(thread);
+        let outermost = {
+            let mut resuming = self.resuming.borrow_mut();
+            resuming.push(id);
+            resuming.len() == 1
+        };
+        if outermost {
+            self.watchdog.resume_started();
+        }
+
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+
+        self.resuming.borrow_mut().pop();
+        if outermost {
+            self.watchdog.resume_ended();
+        }
+
+        let mut threads = self.threads.borrow_mut();
+        if thread.status() == LuaThreadStatus::Resumable {
+            let metrics = threads.entry(id).or_default();
+            metrics.resumes += 1;
+            metrics.cpu_time += elapsed;
+        } else {
+            threads.remove(&id);
+        }
+
+        result
+    }
+
+    pub fn remove(&self, id: ThreadId) {
+        self.threads.borrow_mut().remove(&id);
+    }
+
+    pub fn watchdog(&self) -> &Watchdog {
+        &self.watchdog
+    }
+
+    /**
+        Marks the given thread as blocked on something, until the returned guard is dropped.
+    */
+    pub fn blocked(&self, id: ThreadId, reason: String) -> Blocked {
+        self.blocked
+            .borrow_mut()
+            .entry(id)
+            .or_default()
+            .push(reason);
+        Blocked {
+            id,
+            blocked: Rc::clone(&self.blocked),
+        }
+    }
+
+    /**
+        Marks a future as pending, until the returned guard is dropped.
+    */
+    pub fn pending_future(&self) -> CountGuard {
+        CountGuard::new(&self.futures)
+    }
+
+    /**
+        Marks a future as possibly waking up blocked threads, until the returned guard is dropped.
+
+        Foreground futures, and background futures listening for external events, may do this.
+    */
+    pub fn pending_waker(&self) -> CountGuard {
+        CountGuard::new(&self.wakers)
+    }
+
+    /**
+        Checks if all of the threads waiting on the executor are blocked on other
+        Lua threads, and there are no foreground futures that could unblock them.
+
+        Must only be called when no threads or futures are ready to make progress.
+    */
+    pub fn is_deadlocked(&self, running: &RunningThreads) -> bool {
+        if self.wakers.get() > 0 {
+            return false;
+        }
+        let blocked = self.blocked.borrow();
+        let waiting = running.threads();
+        !waiting.is_empty()
+            && waiting
+                .iter()
+                .all(|thread| blocked.contains_key(&ThreadId::from(thread)))
+    }
+
+    pub fn snapshot(
+        &self,
+        queue_spawn: &SpawnedThreadQueue,
+        queue_defer: &DeferredThreadQueue,
+        running: &RunningThreads,
+    ) -> SchedulerStats {
+        // NOTE: Threads that are currently running are also
+        // tracked as running on the executor, but are not waiting
+        let resuming = self.resuming.borrow();
+        let spawned = queue_spawn.threads();
+        let deferred = queue_defer.threads();
+        let mut waiting = running.threads();
+        waiting.retain(|thread| !resuming.contains(&ThreadId::from(thread)));
+
+        let metrics = self.threads.borrow();
+        let blocked = self.blocked.borrow();
+        let threads = (spawned.iter().map(|t| (t, ThreadState::Spawned)))
+            .chain(deferred.iter().map(|t| (t, ThreadState::Deferred)))
+            .chain(waiting.iter().map(|t| (t, ThreadState::Waiting)))
+            .map(|(thread, state)| {
+                let id = ThreadId::from(thread);
+                let m = metrics.get(&id).copied().unwrap_or_default();
+                ThreadStats {
+                    thread: thread.clone(),
+                    state,
+                    resumes: m.resumes,
+                    cpu_time: m.cpu_time,
+                    blocked_on: blocked.get(&id).and_then(|r| r.last()).cloned(),
+                }
+            })
+            .collect();
+
+        SchedulerStats {
+            status: self.status.get(),
+            spawned: spawned.len(),
+            deferred: deferred.len(),
+            waiting: waiting.len(),
+            running: resuming.len(),
+            futures: self.futures.get(),
+            threads,
+        }
+    }
+}
+
+/**
+    Guard for a counter, see [`Metrics::pending_future`] and [`Metrics::pending_waker`].
+*/
+pub(crate) struct CountGuard {
+    counter: Rc<Cell<usize>>,
+}
+
+impl CountGuard {
+    fn new(counter: &Rc<Cell<usize>>) -> Self {
+        counter.set(counter.get() + 1);
+        Self {
+            counter: Rc::clone(counter),
+        }
+    }
+}
+
+impl Drop for CountGuard {
+    fn drop(&mut self) {
+        self.counter.set(self.counter.get() - 1);
+    }
+}
+
+/**
+    Guard for a blocked thread, see [`Metrics::blocked`].
+*/
+pub(crate) struct Blocked {
+    id: ThreadId,
+    blocked: Rc<RefCell<FxHashMap<ThreadId, Vec<String>>>>,
+}
+
+impl Drop for Blocked {
+    fn drop(&mut self) {
+        let mut blocked = self.blocked.borrow_mut();
+        if let Some(reasons) = blocked.get_mut(&self.id) {
+            reasons.pop();
+            if reasons.is_empty() {
+                blocked.remove(&self.id);
+            }
+        }
+    }
+}
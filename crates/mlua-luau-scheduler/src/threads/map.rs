@@ -26,17 +26,24 @@ impl ThreadEvent {
 #[derive(Clone)]
 pub(crate) struct ThreadMap {
     inner: Rc<RefCell<FxHashMap<ThreadId, ThreadEvent>>>,
+    handled: Rc<RefCell<FxHashMap<ThreadId, usize>>>,
 }
 
 impl ThreadMap {
     pub fn new() -> Self {
         let inner = Rc::new(RefCell::new(FxHashMap::default()));
-        Self { inner }
+        let handled = Rc::new(RefCell::new(FxHashMap::default()));
+        Self { inner, handled }
     }
 
     #[inline(always)]
     pub fn track(&self, id: ThreadId) {
-        self.inner.borrow_mut().insert(id, ThreadEvent::new());
+        // NOTE: Tracking an already tracked thread must not replace its
+        // tracker, since that would leave any existing listeners hanging
+        self.inner
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(ThreadEvent::new);
     }
 
     #[inline(always)]
@@ -71,4 +78,41 @@ impl ThreadMap {
             None
         }
     }
+
+    /**
+        Marks errors of the given thread as handled, until the returned guard is dropped.
+    */
+    #[inline(always)]
+    pub fn handle_errors(&self, id: ThreadId) -> HandledErrors {
+        *self.handled.borrow_mut().entry(id).or_default() += 1;
+        HandledErrors {
+            id,
+            handled: Rc::clone(&self.handled),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_handling_errors(&self, id: ThreadId) -> bool {
+        self.handled.borrow().contains_key(&id)
+    }
+}
+
+/**
+    Guard for errors of a thread being handled, see [`ThreadMap::handle_errors`].
+*/
+pub(crate) struct HandledErrors {
+    id: ThreadId,
+    handled: Rc<RefCell<FxHashMap<ThreadId, usize>>>,
+}
+
+impl Drop for HandledErrors {
+    fn drop(&mut self) {
+        let mut handled = self.handled.borrow_mut();
+        if let Some(count) = handled.get_mut(&self.id) {
+            *count -= 1;
+            if *count == 0 {
+                handled.remove(&self.id);
+            }
+        }
+    }
 }
@@ -1,5 +1,7 @@
 mod id;
 mod map;
+mod running;
 
 pub use id::ThreadId;
 pub(crate) use map::ThreadMap;
+pub(crate) use running::RunningThreads;
@@ -0,0 +1,55 @@
+use std::{cell::RefCell, rc::Rc};
+
+use mlua::prelude::*;
+use rustc_hash::FxHashMap;
+
+use crate::events::{OnceEvent, OnceListener};
+
+use super::id::ThreadId;
+
+/**
+    Threads that are currently being run by the scheduler executor,
+    typically because they are waiting for an async function to complete.
+
+    Lets the executor stop running a thread once it has been cancelled,
+    instead of waiting for whatever the thread was waiting for.
+*/
+#[derive(Clone)]
+pub(crate) struct RunningThreads {
+    inner: Rc<RefCell<FxHashMap<ThreadId, (LuaThread, OnceEvent)>>>,
+}
+
+impl RunningThreads {
+    pub fn new() -> Self {
+        let inner = Rc::new(RefCell::new(FxHashMap::default()));
+        Self { inner }
+    }
+
+    /**
+        Marks the given thread as running, returning a
+        listener that resolves if the thread gets cancelled.
+    */
+    pub fn insert(&self, thread: &LuaThread) -> OnceListener {
+        let event = OnceEvent::new();
+        let listener = event.listen();
+        let id = ThreadId::from(thread);
+        self.inner.borrow_mut().insert(id, (thread.clone(), event));
+        listener
+    }
+
+    pub fn remove(&self, id: ThreadId) {
+        self.inner.borrow_mut().remove(&id);
+    }
+
+    pub fn cancel(&self, id: ThreadId) {
+        let event = self.inner.borrow_mut().remove(&id);
+        if let Some((_, event)) = event {
+            event.notify();
+        }
+    }
+
+    pub fn threads(&self) -> Vec<LuaThread> {
+        let inner = self.inner.borrow();
+        inner.values().map(|(thread, _)| thread.clone()).collect()
+    }
+}
@@ -10,10 +10,16 @@ use mlua::prelude::*;
 use tracing::trace;
 
 use crate::{
+    clock::Clock,
+    context::{Context, ThreadContexts},
+    error_callback::ThreadErrorCallback,
     exit::Exit,
-    queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
+    interrupt::SchedulerInterrupt,
+    priority::{Priority, ThreadPriorities},
+    queue::{BackgroundFuturesQueue, DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
     scheduler::Scheduler,
-    threads::{ThreadId, ThreadMap},
+    stats::{Metrics, SchedulerStats},
+    threads::{RunningThreads, ThreadId, ThreadMap},
 };
 
 /**
@@ -70,6 +76,13 @@ where
     - Setting the exit code and forcibly stopping the scheduler
     - Pushing (spawning) and deferring (pushing to the back) lua threads
     - Tracking and getting the result of lua threads
+    - Handling and reporting errors
+    - Inspecting the current state of the scheduler
+    - Marking the current thread as blocked, for deadlock detection
+    - Measuring time and sleeping, using the clock of the scheduler
+    - Reading and setting context-local values of lua threads
+    - Reading and setting the priorities of lua threads
+    - Setting interrupts that are chained with the watchdog
 */
 pub trait LuaSchedulerExt {
     /**
@@ -141,6 +154,144 @@ pub trait LuaSchedulerExt {
         Panics if called outside of a running [`Scheduler`].
     */
     fn wait_for_thread(&self, id: ThreadId) -> impl Future<Output = ()>;
+
+    /**
+        Marks errors of the given thread as handled until the given future completes, or is
+        dropped, meaning they are not reported to the error callback, since whatever is waiting
+        for the future is expected to handle them instead, such as by raising them in Lua.
+
+        Errors of the thread at any other time are reported to the error callback as usual.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn handling_thread_errors<F>(&self, id: ThreadId, fut: F) -> impl Future<Output = F::Output>
+    where
+        F: Future;
+
+    /**
+        Reports the given error to the error callback of the current scheduler,
+        same as errors of Lua threads, for errors that could not be raised in Lua.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn report_error(&self, error: &LuaError);
+
+    /**
+        Returns a snapshot of the current state of the scheduler.
+
+        See [`Scheduler::stats`] for more information.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn scheduler_stats(&self) -> SchedulerStats;
+
+    /**
+        Marks the current Lua thread as blocked on the given reason
+        until the given future completes, or is dropped.
+
+        This should be used when waiting for something that only other Lua threads can
+        resolve, such as a value being sent through a channel. If every thread waiting
+        on the scheduler is blocked like this, and there are no other futures that may
+        wake them up, the [`Scheduler`] reports a deadlock instead of waiting forever.
+
+        The reason should describe what the thread is waiting for, and will be shown
+        in deadlock reports as "waiting for {reason}", for example "a mutex to unlock".
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn blocked_on<F>(&self, reason: impl Into<String>, fut: F) -> impl Future<Output = F::Output>
+    where
+        F: Future;
+
+    /**
+        Returns the clock of the current scheduler.
+
+        Anything that sleeps or measures time on behalf of Lua should use this clock,
+        so that it respects virtual time, if enabled. See [`Clock`] for more information.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn scheduler_clock(&self) -> Clock;
+
+    /**
+        Sets an interrupt for the Lua state of the current scheduler, same as
+        [`Lua::set_interrupt`], except that it is chained with the interrupt used
+        by the watchdog of the scheduler, instead of replacing it or being replaced.
+
+        Overwrites any previous interrupt set using this method.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn set_scheduler_interrupt<F>(&self, callback: F)
+    where
+        F: Fn(&Lua) -> LuaResult<LuaVmState> + 'static;
+
+    /**
+        Returns the context of the currently running Lua thread.
+
+        This is useful for native functions that are called from Lua, and want to use any
+        context-local values, for example to include a request ID when logging something.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn current_context(&self) -> Context;
+
+    /**
+        Returns the context of the given Lua thread.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn thread_context(&self, thread: &LuaThread) -> Context;
+
+    /**
+        Sets the context of the given Lua thread, replacing any previous context.
+
+        Threads that are spawned, deferred, or resumed as coroutines from Lua inherit the
+        context of the thread that did so automatically, but threads pushed to the scheduler
+        from Rust do not - this may be used to propagate context to such threads manually.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn set_thread_context(&self, thread: &LuaThread, context: Context) -> LuaResult<()>;
+
+    /**
+        Returns the priority of the given Lua thread.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn thread_priority(&self, thread: &LuaThread) -> Priority;
+
+    /**
+        Sets the priority of the given Lua thread, which applies whenever the thread is queued.
+
+        Priorities are not inherited by any threads that the given thread spawns.
+        See [`Priority`] for more information.
+
+        # Panics
+
+        Panics if called outside of a [`Scheduler`].
+    */
+    fn set_thread_priority(&self, thread: &LuaThread, priority: Priority) -> LuaResult<()>;
 }
 
 /**
@@ -149,6 +300,7 @@ pub trait LuaSchedulerExt {
     Provides extra methods on the [`Lua`] struct for:
 
     - Spawning thread-local (`!Send`) futures on the current executor
+    - Spawning thread-local (`!Send`) background futures that do not keep the scheduler running
     - Spawning background (`Send`) futures on the current executor
     - Spawning blocking tasks on a separate thread pool
 */
@@ -237,6 +389,39 @@ pub trait LuaSpawnExt {
     where
         F: Future<Output = ()> + 'static;
 
+    /**
+        Spawns the given thread-local future on the current background executor.
+
+        Unlike [`LuaSpawnExt::spawn_local`], this future will not prevent the [`Scheduler`]
+        it was spawned on from completing, and will be dropped if it is still pending once
+        all other Lua threads and futures have completed. This makes it useful for listening
+        to external events, such as OS signals, that should not keep the scheduler running.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn spawn_local_background<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static;
+
+    /**
+        Spawns the given thread-local future on the current background executor,
+        as a listener for external events that may wake up blocked Lua threads.
+
+        Just like [`LuaSpawnExt::spawn_local_background`], this future will not prevent
+        the [`Scheduler`] from completing. Unlike it, the scheduler will not report a
+        deadlock while this future is pending, since it may still wake up Lua threads
+        that are blocked on each other, for example by running an OS signal handler.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn spawn_local_listener<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static;
+
     /**
         Spawns the given blocking function and returns its [`Task`].
 
@@ -331,6 +516,108 @@ impl LuaSchedulerExt for Lua {
             .expect("lua threads results can only be retrieved from within an active scheduler");
         map.listen(id)
     }
+
+    fn handling_thread_errors<F>(&self, id: ThreadId, fut: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        let map = self
+            .app_data_ref::<ThreadMap>()
+            .expect("lua thread errors can only be handled from within an active scheduler");
+        let handled = map.handle_errors(id);
+        async move {
+            let output = fut.await;
+            drop(handled);
+            output
+        }
+    }
+
+    fn report_error(&self, error: &LuaError) {
+        let callback = self
+            .app_data_ref::<ThreadErrorCallback>()
+            .expect("errors can only be reported from within an active scheduler");
+        callback.call(error);
+    }
+
+    fn scheduler_stats(&self) -> SchedulerStats {
+        let metrics = self
+            .app_data_ref::<Metrics>()
+            .expect("scheduler stats can only be retrieved from within a scheduler");
+        let queue_spawn = self
+            .app_data_ref::<SpawnedThreadQueue>()
+            .expect("scheduler stats can only be retrieved from within a scheduler");
+        let queue_defer = self
+            .app_data_ref::<DeferredThreadQueue>()
+            .expect("scheduler stats can only be retrieved from within a scheduler");
+        let running = self
+            .app_data_ref::<RunningThreads>()
+            .expect("scheduler stats can only be retrieved from within a scheduler");
+        metrics.snapshot(&queue_spawn, &queue_defer, &running)
+    }
+
+    fn blocked_on<F>(&self, reason: impl Into<String>, fut: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        let metrics = self
+            .app_data_ref::<Metrics>()
+            .expect("threads can only be blocked from within an active scheduler");
+        let blocked = metrics.blocked(ThreadId::from(&self.current_thread()), reason.into());
+        async move {
+            let output = fut.await;
+            drop(blocked);
+            output
+        }
+    }
+
+    fn scheduler_clock(&self) -> Clock {
+        self.app_data_ref::<Clock>()
+            .expect("scheduler clock can only be retrieved from within a scheduler")
+            .clone()
+    }
+
+    fn set_scheduler_interrupt<F>(&self, callback: F)
+    where
+        F: Fn(&Lua) -> LuaResult<LuaVmState> + 'static,
+    {
+        let interrupt = self
+            .app_data_ref::<SchedulerInterrupt>()
+            .expect("scheduler interrupts can only be set for lua states with a scheduler")
+            .clone();
+        interrupt.replace(self, callback);
+    }
+
+    fn current_context(&self) -> Context {
+        self.thread_context(&self.current_thread())
+    }
+
+    fn thread_context(&self, thread: &LuaThread) -> Context {
+        let contexts = self
+            .app_data_ref::<ThreadContexts>()
+            .expect("thread contexts can only be retrieved from within a scheduler");
+        contexts.get(thread)
+    }
+
+    fn set_thread_context(&self, thread: &LuaThread, context: Context) -> LuaResult<()> {
+        let contexts = self
+            .app_data_ref::<ThreadContexts>()
+            .expect("thread contexts can only be set from within a scheduler");
+        contexts.set(thread, context)
+    }
+
+    fn thread_priority(&self, thread: &LuaThread) -> Priority {
+        let priorities = self
+            .app_data_ref::<ThreadPriorities>()
+            .expect("thread priorities can only be retrieved from within a scheduler");
+        priorities.get(thread)
+    }
+
+    fn set_thread_priority(&self, thread: &LuaThread, priority: Priority) -> LuaResult<()> {
+        let priorities = self
+            .app_data_ref::<ThreadPriorities>()
+            .expect("thread priorities can only be set from within a scheduler");
+        priorities.set(thread, priority)
+    }
 }
 
 impl LuaSpawnExt for Lua {
@@ -359,6 +646,31 @@ impl LuaSpawnExt for Lua {
         queue.push_item(fut);
     }
 
+    fn spawn_local_background<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let queue = self
+            .app_data_ref::<BackgroundFuturesQueue>()
+            .expect("tasks can only be spawned within an active scheduler");
+        trace!("spawning local background task on executor");
+        queue.push_item(fut);
+    }
+
+    fn spawn_local_listener<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let waker = self
+            .app_data_ref::<Metrics>()
+            .expect("tasks can only be spawned within an active scheduler")
+            .pending_waker();
+        self.spawn_local_background(async move {
+            fut.await;
+            drop(waker);
+        });
+    }
+
     fn spawn_blocking<F, T>(&self, f: F) -> Task<T>
     where
         F: FnOnce() -> T + Send + 'static,
@@ -1,7 +1,23 @@
-use futures_lite::StreamExt;
+use std::fmt::Write;
+
+use futures_lite::{StreamExt, future::poll_fn};
 use mlua::prelude::*;
 use tracing::instrument;
 
+use crate::stats::Metrics;
+
+/**
+    Creates a new table with weak keys, which is useful for associating values with
+    Lua threads, without preventing the threads from being garbage collected.
+*/
+pub(crate) fn create_weak_keys_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    let meta = lua.create_table()?;
+    meta.raw_set("__mode", "k")?;
+    table.set_metatable(Some(meta))?;
+    Ok(table)
+}
+
 /**
     Runs a Lua thread until it manually yields (using coroutine.yield), errors, or completes.
 
@@ -13,8 +29,12 @@ use tracing::instrument;
 pub(crate) async fn run_until_yield(
     thread: LuaThread,
     args: LuaMultiValue,
+    metrics: &Metrics,
 ) -> Option<LuaResult<LuaMultiValue>> {
-    let mut stream = thread.into_async(args).expect("thread must be resumable");
+    let mut stream = thread
+        .clone()
+        .into_async(args)
+        .expect("thread must be resumable");
     /*
         NOTE: It is very important that we drop the thread/stream as
         soon as we are done, it takes up valuable Lua registry space
@@ -26,8 +46,59 @@ pub(crate) async fn run_until_yield(
 
         Even though we are converting into a stream, and then immediately running it,
         the future may still be cancelled before it is polled, which gives us None.
+
+        Every poll of the stream resumes the thread, so we measure each one separately.
     */
-    stream.next().await
+    poll_fn(|cx| metrics.measure(&thread, || stream.poll_next(cx))).await
+}
+
+/**
+    Returns the stack traceback of the given thread, using the `debug.traceback` global.
+
+    Threads waiting for async functions are yielded from within an internal polling
+    function, which is not useful to show in stack traces, so those frames are omitted.
+*/
+pub(crate) fn traceback(lua: &Lua, thread: &LuaThread) -> LuaResult<String> {
+    let traceback = lua
+        .globals()
+        .get::<LuaTable>("debug")?
+        .get::<LuaFunction>("traceback")?
+        .call::<String>(thread)?;
+    let lines = traceback
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with("__mlua"))
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
+
+/**
+    Returns the stack traceback of the currently running Lua code.
+
+    Unlike [`traceback`], this does not call into Lua, and is
+    safe to use from within callbacks such as interrupts.
+*/
+pub(crate) fn current_traceback(lua: &Lua) -> String {
+    let mut trace = String::new();
+    let mut level = 0;
+    while let Some(line) = lua.inspect_stack(level, |debug| {
+        let source = debug.source();
+        let source = source.short_src.as_deref().unwrap_or("?");
+        let mut line = match debug.current_line() {
+            Some(current) => format!("{source}:{current}"),
+            None => source.to_string(),
+        };
+        if let Some(name) = debug.names().name.as_deref() {
+            write!(line, " function {name}").ok();
+        }
+        line
+    }) {
+        if !trace.is_empty() {
+            trace.push('\n');
+        }
+        trace.push_str(&line);
+        level += 1;
+    }
+    trace
 }
 
 /**
@@ -0,0 +1,123 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct WatchdogInner {
+    base: Instant,
+    // Time at which the current resume started, in nanoseconds since base, or zero if not resuming
+    started: AtomicU64,
+    // Time at which the last reported resume started, so that each resume is only reported once
+    reported: AtomicU64,
+    overdue: AtomicBool,
+}
+
+/**
+    Watches for Lua threads that run for too long without yielding.
+
+    Resumes are timed on the scheduler thread, while checking for resumes that have
+    been running for too long happens on a separate OS thread, since the scheduler
+    thread is busy running Lua code, and can not check anything by itself.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Watchdog {
+    inner: Arc<WatchdogInner>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(WatchdogInner {
+                base: Instant::now(),
+                started: AtomicU64::new(0),
+                reported: AtomicU64::new(0),
+                overdue: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        // NOTE: Adding one guarantees that zero is never a valid start time
+        u64::try_from(self.inner.base.elapsed().as_nanos())
+            .unwrap_or(u64::MAX)
+            .saturating_add(1)
+    }
+
+    pub fn resume_started(&self) {
+        self.inner.started.store(self.now(), Ordering::SeqCst);
+    }
+
+    pub fn resume_ended(&self) {
+        self.inner.started.store(0, Ordering::SeqCst);
+        self.inner.overdue.store(false, Ordering::SeqCst);
+    }
+
+    /**
+        Returns how long the current resume has been running for,
+        if it has been running for too long and was not yet reported.
+    */
+    pub fn take_overdue(&self) -> Option<Duration> {
+        if !self.inner.overdue.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        match self.inner.started.load(Ordering::SeqCst) {
+            0 => None,
+            started => Some(Duration::from_nanos(self.now() - started)),
+        }
+    }
+
+    /**
+        Starts watching resumes on a separate OS thread, marking resumes
+        that run for longer than the given timeout as overdue.
+
+        Watching stops once the returned guard is dropped.
+    */
+    pub fn start(&self, timeout: Duration) -> WatchdogGuard {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let interval = (timeout / 4).clamp(Duration::from_millis(1), Duration::from_millis(100));
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+
+        let this = self.clone();
+        let inner_stopped = Arc::clone(&stopped);
+        let handle = thread::spawn(move || {
+            while !inner_stopped.load(Ordering::SeqCst) {
+                thread::park_timeout(interval);
+                let started = this.inner.started.load(Ordering::SeqCst);
+                if started != 0
+                    && this.now().saturating_sub(started) >= timeout
+                    && this.inner.reported.swap(started, Ordering::SeqCst) != started
+                {
+                    this.inner.overdue.store(true, Ordering::SeqCst);
+                }
+            }
+        });
+
+        WatchdogGuard {
+            stopped,
+            handle: Some(handle),
+        }
+    }
+}
+
+/**
+    Guard for a running watchdog, see [`Watchdog::start`].
+*/
+pub(crate) struct WatchdogGuard {
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for WatchdogGuard {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            handle.join().ok();
+        }
+    }
+}